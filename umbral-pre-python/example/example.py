@@ -84,3 +84,13 @@ assert cfrag1.verify(capsule, alice_pk, bob_pk, signing_pk)
 plaintext_bob = umbral_pre.decrypt_reencrypted(
     bob_sk, alice_pk, capsule, [cfrag0, cfrag1], ciphertext)
 assert plaintext_bob == plaintext
+
+# Alternatively, Bob can collect cfrags one by one as they arrive.
+# The collector verifies each cfrag and tracks the progress
+# toward the threshold.
+collector = umbral_pre.CfragCollector(
+    capsule, bob_sk, alice_pk, signing_pk)
+collector.add_cfrag(cfrag0)
+collector.add_cfrag(cfrag1)
+assert collector.is_ready()
+assert collector.decrypt(ciphertext) == plaintext
@@ -1,3 +1,4 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::PyBytes;
 use pyo3::wrap_pyfunction;
@@ -15,6 +16,42 @@ impl SecretKey {
             backend: umbral_pre::SecretKey::random(),
         }
     }
+
+    pub fn to_encrypted_bytes(
+        &self,
+        py: Python,
+        password: &[u8],
+        key_type: &str,
+    ) -> PyResult<PyObject> {
+        let backend_key_type = key_type_from_str(key_type)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown key type: {}", key_type)))?;
+        let data = self.backend.to_encrypted_bytes(password, backend_key_type);
+        Ok(PyBytes::new(py, &data).into())
+    }
+
+    #[staticmethod]
+    pub fn from_encrypted_bytes(data: &[u8], password: &[u8]) -> PyResult<(Self, &'static str)> {
+        umbral_pre::SecretKey::from_encrypted_bytes(data, password)
+            .map(|(backend, key_type)| (Self { backend }, key_type_to_str(key_type)))
+            .map_err(|err| PyValueError::new_err(format!("{}", err)))
+    }
+}
+
+fn key_type_from_str(key_type: &str) -> Option<umbral_pre::SecretKeyType> {
+    match key_type {
+        "delegating" => Some(umbral_pre::SecretKeyType::Delegating),
+        "signing" => Some(umbral_pre::SecretKeyType::Signing),
+        "receiving" => Some(umbral_pre::SecretKeyType::Receiving),
+        _ => None,
+    }
+}
+
+fn key_type_to_str(key_type: umbral_pre::SecretKeyType) -> &'static str {
+    match key_type {
+        umbral_pre::SecretKeyType::Delegating => "delegating",
+        umbral_pre::SecretKeyType::Signing => "signing",
+        umbral_pre::SecretKeyType::Receiving => "receiving",
+    }
 }
 
 #[pyclass(module = "umbral")]
@@ -159,6 +196,16 @@ impl CapsuleFrag {
             &receiving_pk.backend,
         )
     }
+
+    #[getter]
+    pub fn threshold(&self) -> usize {
+        self.backend.threshold()
+    }
+
+    #[getter]
+    pub fn num_kfrags(&self) -> usize {
+        self.backend.num_kfrags()
+    }
 }
 
 #[pyfunction]
@@ -177,19 +224,69 @@ pub fn decrypt_reencrypted(
     capsule: &Capsule,
     cfrags: Vec<CapsuleFrag>,
     ciphertext: &[u8],
-) -> Option<PyObject> {
+) -> PyResult<PyObject> {
     let backend_cfrags: Vec<umbral_pre::CapsuleFrag> =
         cfrags.iter().cloned().map(|cfrag| cfrag.backend).collect();
-    let res = umbral_pre::decrypt_reencrypted(
+    umbral_pre::decrypt_reencrypted(
         &decrypting_sk.backend,
         &delegating_pk.backend,
         &capsule.backend,
         &backend_cfrags,
         ciphertext,
-    );
-    match res {
-        Some(plaintext) => Some(PyBytes::new(py, &plaintext).into()),
-        None => None,
+    )
+    .map(|plaintext| PyBytes::new(py, &plaintext).into())
+    .map_err(|err| PyValueError::new_err(format!("{}", err)))
+}
+
+#[pyclass(module = "umbral")]
+pub struct CfragCollector {
+    backend: umbral_pre::CfragCollector,
+}
+
+#[pymethods]
+impl CfragCollector {
+    #[new]
+    pub fn new(
+        capsule: &Capsule,
+        decrypting_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        signing_pk: &PublicKey,
+    ) -> Self {
+        Self {
+            backend: umbral_pre::CfragCollector::new(
+                &capsule.backend,
+                &decrypting_sk.backend,
+                &delegating_pk.backend,
+                &signing_pk.backend,
+            ),
+        }
+    }
+
+    pub fn add_cfrag(&mut self, cfrag: &CapsuleFrag) -> PyResult<()> {
+        self.backend
+            .add_cfrag(&cfrag.backend)
+            .map_err(|err| PyValueError::new_err(format!("{}", err)))
+    }
+
+    #[getter]
+    pub fn received(&self) -> usize {
+        self.backend.received()
+    }
+
+    #[getter]
+    pub fn threshold(&self) -> Option<usize> {
+        self.backend.threshold()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.backend.is_ready()
+    }
+
+    pub fn decrypt(&self, py: Python, ciphertext: &[u8]) -> PyResult<PyObject> {
+        self.backend
+            .decrypt(ciphertext)
+            .map(|plaintext| PyBytes::new(py, &plaintext).into())
+            .map_err(|err| PyValueError::new_err(format!("{}", err)))
     }
 }
 
@@ -199,6 +296,7 @@ fn _umbral(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<SecretKey>()?;
     m.add_class::<PublicKey>()?;
     m.add_class::<Parameters>()?;
+    m.add_class::<CfragCollector>()?;
     m.add_function(wrap_pyfunction!(encrypt, m)?).unwrap();
     m.add_function(wrap_pyfunction!(decrypt_original, m)?)
         .unwrap();
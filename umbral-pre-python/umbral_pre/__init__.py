@@ -2,6 +2,7 @@ from ._umbral import (
     SecretKey,
     PublicKey,
     Parameters,
+    CfragCollector,
     encrypt,
     decrypt_original,
     decrypt_reencrypted,
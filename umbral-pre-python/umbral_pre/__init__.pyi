@@ -6,6 +6,13 @@ class SecretKey:
     def random() -> SecretKey:
         ...
 
+    def to_encrypted_bytes(self, password: bytes, key_type: str) -> bytes:
+        ...
+
+    @staticmethod
+    def from_encrypted_bytes(data: bytes, password: bytes) -> Tuple[SecretKey, str]:
+        ...
+
 class PublicKey:
     @staticmethod
     def from_secret_key(sk: SecretKey) -> PublicKey:
@@ -59,6 +66,10 @@ class CapsuleFrag:
             ) -> bool:
         ...
 
+    threshold: int
+
+    num_kfrags: int
+
 
 def reencrypt(capsule: Capsule, kfrag: KeyFrag, metadata: Optional[bytes]) -> CapsuleFrag:
     ...
@@ -70,5 +81,29 @@ def decrypt_reencrypted(
         capsule: Capsule,
         cfrags: Sequence[CapsuleFrag],
         ciphertext: bytes,
-        ) -> Optional[bytes]:
+        ) -> bytes:
     ...
+
+
+class CfragCollector:
+    def __init__(
+            self,
+            capsule: Capsule,
+            decrypting_sk: SecretKey,
+            delegating_pk: PublicKey,
+            signing_pk: PublicKey,
+            ):
+        ...
+
+    def add_cfrag(self, cfrag: CapsuleFrag) -> None:
+        ...
+
+    received: int
+
+    threshold: Optional[int]
+
+    def is_ready(self) -> bool:
+        ...
+
+    def decrypt(self, ciphertext: bytes) -> bytes:
+        ...
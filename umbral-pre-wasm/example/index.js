@@ -85,10 +85,12 @@ console.assert(cfrag1.verify(capsule, alice_pk, bob_pk, signing_pk), "cfrag1 is
 
 // Another deviation from the Rust API.
 // wasm-pack does not support taking arrays as arguments,
-// so we build a capsule+cfrags object before decryption.
-let plaintext_bob = capsule
-    .with_cfrag(cfrag0)
-    .with_cfrag(cfrag1)
-    .decrypt_reencrypted(bob_sk, alice_pk, ciphertext);
+// so we add cfrags to a collector one by one before decryption.
+// The collector verifies each cfrag as it is added.
+let collector = new umbral.CfragCollector(capsule, bob_sk, alice_pk, signing_pk);
+collector.add_cfrag(cfrag0);
+collector.add_cfrag(cfrag1);
+console.assert(collector.is_ready(), "not enough cfrags collected");
+let plaintext_bob = collector.decrypt_reencrypted(ciphertext);
 
 console.assert(dec.decode(plaintext_bob) == plaintext, "decrypt_reencrypted() failed");
@@ -10,6 +10,8 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
 
 use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
 use alloc::{vec, vec::Vec};
 
 #[wasm_bindgen]
@@ -22,6 +24,70 @@ impl SecretKey {
         console_error_panic_hook::set_once(); // TODO (#16): find a better place to initialize it
         Self(umbral_pre::SecretKey::random())
     }
+
+    /// Encrypts the secret key with a password for storage.
+    /// `key_type` is one of `"delegating"`, `"signing"` or `"receiving"`.
+    #[wasm_bindgen]
+    pub fn to_encrypted_bytes(
+        &self,
+        password: &[u8],
+        key_type: &str,
+    ) -> Result<Box<[u8]>, JsValue> {
+        let backend_key_type = key_type_from_str(key_type)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown key type: {}", key_type)))?;
+        Ok(self.0.to_encrypted_bytes(password, backend_key_type))
+    }
+
+    /// Restores the secret key encrypted with `to_encrypted_bytes()`.
+    #[wasm_bindgen]
+    pub fn from_encrypted_bytes(
+        data: &[u8],
+        password: &[u8],
+    ) -> Result<DecryptedSecretKey, JsValue> {
+        umbral_pre::SecretKey::from_encrypted_bytes(data, password)
+            .map(|(sk, key_type)| DecryptedSecretKey {
+                secret_key: Self(sk),
+                key_type: key_type_to_str(key_type),
+            })
+            .map_err(|err| JsValue::from_str(&format!("{}", err)))
+    }
+}
+
+fn key_type_from_str(key_type: &str) -> Option<umbral_pre::SecretKeyType> {
+    match key_type {
+        "delegating" => Some(umbral_pre::SecretKeyType::Delegating),
+        "signing" => Some(umbral_pre::SecretKeyType::Signing),
+        "receiving" => Some(umbral_pre::SecretKeyType::Receiving),
+        _ => None,
+    }
+}
+
+fn key_type_to_str(key_type: umbral_pre::SecretKeyType) -> &'static str {
+    match key_type {
+        umbral_pre::SecretKeyType::Delegating => "delegating",
+        umbral_pre::SecretKeyType::Signing => "signing",
+        umbral_pre::SecretKeyType::Receiving => "receiving",
+    }
+}
+
+#[wasm_bindgen]
+pub struct DecryptedSecretKey {
+    secret_key: SecretKey,
+    key_type: &'static str,
+}
+
+#[wasm_bindgen]
+impl DecryptedSecretKey {
+    // `SecretKey` is not `Copy`, so the fields cannot be made public.
+    #[wasm_bindgen(getter)]
+    pub fn secret_key(&self) -> SecretKey {
+        SecretKey(self.secret_key.0.clone())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn key_type(&self) -> String {
+        self.key_type.into()
+    }
 }
 
 #[wasm_bindgen]
@@ -58,9 +124,8 @@ pub struct Capsule(umbral_pre::Capsule);
 
 #[wasm_bindgen]
 impl Capsule {
-    // TODO (#23): have to add cfrags one by one since `wasm_bindgen` currently does not support
-    // Vec<CustomStruct> as a parameter.
-    // Will probably be fixed along with https://github.com/rustwasm/wasm-bindgen/issues/111
+    /// @deprecated Use `CfragCollector` instead, which verifies the cfrags as they are added.
+    /// Will be removed in the next release.
     #[wasm_bindgen]
     pub fn with_cfrag(&self, cfrag: &CapsuleFrag) -> CapsuleWithFrags {
         CapsuleWithFrags {
@@ -91,8 +156,75 @@ impl CapsuleFrag {
             &receiving_pubkey.0,
         )
     }
+
+    #[wasm_bindgen(getter)]
+    pub fn threshold(&self) -> usize {
+        self.0.threshold()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn num_kfrags(&self) -> usize {
+        self.0.num_kfrags()
+    }
+}
+
+// TODO (#23): have to add cfrags one by one since `wasm_bindgen` currently does not support
+// Vec<CustomStruct> as a parameter.
+// Will probably be fixed along with https://github.com/rustwasm/wasm-bindgen/issues/111
+#[wasm_bindgen]
+pub struct CfragCollector(umbral_pre::CfragCollector);
+
+#[wasm_bindgen]
+impl CfragCollector {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        capsule: &Capsule,
+        decrypting_key: &SecretKey,
+        delegating_pk: &PublicKey,
+        signing_pk: &PublicKey,
+    ) -> Self {
+        Self(umbral_pre::CfragCollector::new(
+            &capsule.0,
+            &decrypting_key.0,
+            &delegating_pk.0,
+            &signing_pk.0,
+        ))
+    }
+
+    #[wasm_bindgen]
+    pub fn add_cfrag(&mut self, cfrag: &CapsuleFrag) -> Result<(), JsValue> {
+        self.0
+            .add_cfrag(&cfrag.0)
+            .map_err(|err| JsValue::from_str(&format!("{}", err)))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn received(&self) -> usize {
+        self.0.received()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn threshold(&self) -> Option<usize> {
+        self.0.threshold()
+    }
+
+    #[wasm_bindgen]
+    pub fn is_ready(&self) -> bool {
+        self.0.is_ready()
+    }
+
+    #[wasm_bindgen]
+    pub fn decrypt_reencrypted(&self, ciphertext: &[u8]) -> Result<Box<[u8]>, JsValue> {
+        self.0
+            .decrypt(ciphertext)
+            .map_err(|err| JsValue::from_str(&format!("{}", err)))
+    }
 }
 
+/// @deprecated Use `CfragCollector` instead, which verifies the cfrags as they are added.
+/// Will be removed in the next release.
+///
+/// Does not verify the cfrags, same as before the deprecation.
 #[wasm_bindgen]
 pub struct CapsuleWithFrags {
     capsule: Capsule,
@@ -127,6 +259,7 @@ impl CapsuleWithFrags {
             backend_cfrags.as_slice(),
             ciphertext,
         )
+        .ok()
     }
 }
 
@@ -1,17 +1,78 @@
 use crate::capsule_frag::CapsuleFrag;
 use crate::curve::{CurvePoint, CurveScalar, PublicKey, SecretKey};
 use crate::hashing::ScalarDigest;
-use crate::hashing_ds::{hash_to_polynomial_arg, hash_to_shared_secret};
+use crate::hashing_ds::{
+    hash_to_polynomial_arg, hash_to_public_polynomial_arg, hash_to_shared_secret,
+};
+use crate::key_frag::KeyFragID;
 use crate::params::Parameters;
 use crate::traits::SerializableToArray;
 
 use alloc::vec::Vec;
+use core::fmt;
 
 use generic_array::sequence::Concat;
 use generic_array::GenericArray;
 use typenum::op;
 
+/// Errors that can happen when opening a [`Capsule`] using reencrypted
+/// [`CapsuleFrag`] objects.
+#[derive(Debug, PartialEq)]
+pub enum OpenReencryptedError {
+    /// An empty capsule fragment list is given.
+    NoCapsuleFrags,
+    /// Capsule fragments are mismatched (originated from [`KeyFrag`](crate::KeyFrag) objects
+    /// generated by different [`generate_kfrags`](crate::generate_kfrags) calls).
+    MismatchedCapsuleFrags,
+    /// Some of the given capsule fragments are repeated.
+    RepeatingCapsuleFrags,
+    /// Fewer capsule fragments than the threshold they were created with are given.
+    NotEnoughCapsuleFrags {
+        /// The number of fragments necessary for decryption.
+        threshold: usize,
+        /// The number of distinct fragments given.
+        received: usize,
+    },
+    /// An internally hashed value is zero.
+    /// See [rust-umbral#39](https://github.com/nucypher/rust-umbral/issues/39).
+    ZeroHash,
+    /// Internal validation of the result has failed.
+    /// Can be caused by an incorrect (possibly modified) capsule
+    /// or some of the capsule fragments.
+    ValidationFailed,
+}
+
+impl fmt::Display for OpenReencryptedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCapsuleFrags => write!(f, "Empty CapsuleFrag sequence"),
+            Self::MismatchedCapsuleFrags => write!(f, "CapsuleFrags are not pairwise consistent"),
+            Self::RepeatingCapsuleFrags => write!(f, "Some of the CapsuleFrags are repeated"),
+            Self::NotEnoughCapsuleFrags {
+                threshold,
+                received,
+            } => write!(
+                f,
+                "Not enough CapsuleFrags: need {} more (threshold is {})",
+                threshold - received,
+                threshold
+            ),
+            Self::ZeroHash => write!(f, "An internally hashed value is zero"),
+            Self::ValidationFailed => write!(f, "Internal validation failed"),
+        }
+    }
+}
+
 /// Encapsulated symmetric key used to encrypt the plaintext.
+///
+/// Note that a capsule is linkable: the same capsule is sent to every proxy
+/// reencrypting it, and it cannot be re-randomized by anyone but the encrypting party.
+/// Its signature is a proof of knowledge of the ephemeral scalars behind `point_e`
+/// and `point_v`, which are discarded after encryption, and blinding the points
+/// (even while keeping their sum, and thus the encapsulated key, the same)
+/// changes the challenge the proof depends on.
+/// Besides, the capsule is used as the associated data of the ciphertext,
+/// so a different capsule would not decrypt it anyway.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Capsule {
     pub(crate) params: Parameters,
@@ -76,19 +137,29 @@ impl Capsule {
 
     /// Generates a symmetric key and its associated KEM ciphertext
     pub(crate) fn from_pubkey(params: &Parameters, pk: &PublicKey) -> (Capsule, CurvePoint) {
-        let g = CurvePoint::generator();
-
         let priv_r = CurveScalar::random_nonzero();
-        let pub_r = &g * &priv_r;
-
         let priv_u = CurveScalar::random_nonzero();
-        let pub_u = &g * &priv_u;
+        Self::from_pubkey_and_scalars(params, pk, &priv_r, &priv_u)
+    }
+
+    /// Generates a symmetric key and its associated KEM ciphertext
+    /// using the given ephemeral scalars, which must be non-zero and never reused.
+    pub(crate) fn from_pubkey_and_scalars(
+        params: &Parameters,
+        pk: &PublicKey,
+        priv_r: &CurveScalar,
+        priv_u: &CurveScalar,
+    ) -> (Capsule, CurvePoint) {
+        let g = CurvePoint::generator();
+
+        let pub_r = &g * priv_r;
+        let pub_u = &g * priv_u;
 
         let h = ScalarDigest::new().chain_points(&[pub_r, pub_u]).finalize();
 
-        let s = &priv_u + &(&priv_r * &h);
+        let s = priv_u + &(priv_r * &h);
 
-        let shared_key = &pk.to_point() * &(&priv_r + &priv_u);
+        let shared_key = &pk.to_point() * &(priv_r + priv_u);
 
         let capsule = Self {
             params: *params,
@@ -105,30 +176,108 @@ impl Capsule {
         &(&self.point_e + &self.point_v) * &private_key.to_secret_scalar()
     }
 
-    #[allow(clippy::many_single_char_names)]
     pub(crate) fn open_reencrypted(
         &self,
         receiving_sk: &SecretKey,
         delegating_pk: &PublicKey,
         cfrags: &[CapsuleFrag],
-    ) -> Option<CurvePoint> {
+    ) -> Result<CurvePoint, OpenReencryptedError> {
+        let (e_prime, v_prime, d) = self.combine_cfrags(receiving_sk, delegating_pk, cfrags)?;
+        let shared_key = &(&e_prime + &v_prime) * &d;
+        Ok(shared_key)
+    }
+
+    /// Opens the capsule given the fragments' precursor multiplied by the receiving secret key
+    /// instead of the key itself.
+    pub(crate) fn open_reencrypted_with_dh_point(
+        &self,
+        receiving_pk: &PublicKey,
+        dh_point: &CurvePoint,
+        delegating_pk: &PublicKey,
+        cfrags: &[CapsuleFrag],
+    ) -> Result<CurvePoint, OpenReencryptedError> {
+        let (e_prime, v_prime, d) =
+            self.combine_cfrags_with_dh_point(receiving_pk, dh_point, delegating_pk, cfrags)?;
+        let shared_key = &(&e_prime + &v_prime) * &d;
+        Ok(shared_key)
+    }
+
+    /// Combines the capsule fragments and checks the result against the capsule.
+    /// Returns the combined points `e'` and `v'`, and the secret value `d`
+    /// derived from the fragments' precursor and `receiving_sk`.
+    ///
+    /// Fragments reencrypted from a [`ReencryptedCapsule`](crate::ReencryptedCapsule)
+    /// combine into points that satisfy the same check against the original capsule,
+    /// so this works at any re-delegation level.
+    pub(crate) fn combine_cfrags(
+        &self,
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        cfrags: &[CapsuleFrag],
+    ) -> Result<(CurvePoint, CurvePoint, CurveScalar), OpenReencryptedError> {
+        let precursor = cfrags
+            .first()
+            .ok_or(OpenReencryptedError::NoCapsuleFrags)?
+            .precursor;
+        let receiving_pk = PublicKey::from_secret_key(receiving_sk);
+        let dh_point = &precursor * &receiving_sk.to_secret_scalar();
+        self.combine_cfrags_with_dh_point(&receiving_pk, &dh_point, delegating_pk, cfrags)
+    }
+
+    /// Same as [`combine_cfrags()`](`Self::combine_cfrags()`), but takes the fragments' precursor
+    /// multiplied by the receiving secret key, so that the key itself can be shared.
+    /// An incorrect `dh_point` leads to [`OpenReencryptedError::ValidationFailed`].
+    #[allow(clippy::many_single_char_names)]
+    pub(crate) fn combine_cfrags_with_dh_point(
+        &self,
+        receiving_pk: &PublicKey,
+        dh_point: &CurvePoint,
+        delegating_pk: &PublicKey,
+        cfrags: &[CapsuleFrag],
+    ) -> Result<(CurvePoint, CurvePoint, CurveScalar), OpenReencryptedError> {
         if cfrags.is_empty() {
-            return None;
+            return Err(OpenReencryptedError::NoCapsuleFrags);
         }
 
         let precursor = cfrags[0].precursor;
+        let threshold = cfrags[0].threshold();
+        let public_share_indices = cfrags[0].proof.public_share_indices;
+
+        if !cfrags.iter().all(|cfrag| {
+            cfrag.precursor == precursor
+                && cfrag.threshold() == threshold
+                && cfrag.proof.public_share_indices == public_share_indices
+        }) {
+            return Err(OpenReencryptedError::MismatchedCapsuleFrags);
+        }
 
-        if !cfrags.iter().all(|cfrag| cfrag.precursor == precursor) {
-            return None;
+        for (i, cfrag) in cfrags.iter().enumerate() {
+            if cfrags[..i]
+                .iter()
+                .any(|other| other.kfrag_id == cfrag.kfrag_id)
+            {
+                return Err(OpenReencryptedError::RepeatingCapsuleFrags);
+            }
         }
 
-        let pub_key = PublicKey::from_secret_key(receiving_sk).to_point();
-        let dh_point = &precursor * &receiving_sk.to_secret_scalar();
+        if cfrags.len() < threshold {
+            return Err(OpenReencryptedError::NotEnoughCapsuleFrags {
+                threshold,
+                received: cfrags.len(),
+            });
+        }
+
+        let pub_key = receiving_pk.to_point();
+        let dh_point = *dh_point;
 
         // Combination of CFrags via Shamir's Secret Sharing reconstruction
         let mut lc = Vec::<CurveScalar>::with_capacity(cfrags.len());
         for cfrag in cfrags {
-            let coeff = hash_to_polynomial_arg(&precursor, &pub_key, &dh_point, &cfrag.kfrag_id);
+            let coeff = if public_share_indices {
+                hash_to_public_polynomial_arg(&precursor, &cfrag.kfrag_id)
+            } else {
+                hash_to_polynomial_arg(&precursor, &pub_key, &dh_point, &cfrag.kfrag_id)
+            };
             lc.push(coeff);
         }
 
@@ -137,7 +286,7 @@ impl Capsule {
         for (i, cfrag) in (&cfrags).iter().enumerate() {
             // There is a minuscule probability that two elements of `lc` are equal,
             // in which case we'd rather fail gracefully.
-            let lambda_i = lambda_coeff(&lc, i)?;
+            let lambda_i = lambda_coeff(&lc, i).ok_or(OpenReencryptedError::ZeroHash)?;
             e_prime = &e_prime + &(&cfrag.point_e1 * &lambda_i);
             v_prime = &v_prime + &(&cfrag.point_v1 * &lambda_i);
         }
@@ -145,6 +294,21 @@ impl Capsule {
         // Secret value 'd' allows to make Umbral non-interactive
         let d = hash_to_shared_secret(&precursor, &pub_key, &dh_point);
 
+        self.verify_combined(delegating_pk, &e_prime, &v_prime, &d)?;
+
+        Ok((e_prime, v_prime, d))
+    }
+
+    /// Checks the combined points `e'` and `v'` and the secret value `d`
+    /// (see [`combine_cfrags()`](`Self::combine_cfrags()`)) against the capsule.
+    #[allow(clippy::many_single_char_names)]
+    pub(crate) fn verify_combined(
+        &self,
+        delegating_pk: &PublicKey,
+        e_prime: &CurvePoint,
+        v_prime: &CurvePoint,
+        d: &CurveScalar,
+    ) -> Result<(), OpenReencryptedError> {
         let e = self.point_e;
         let v = self.point_v;
         let s = self.signature;
@@ -158,18 +322,148 @@ impl Capsule {
         // Technically, it is supposed to be non-zero by the choice of `precursor`,
         // but if is was somehow replaced by an incorrect value,
         // we'd rather fail gracefully than panic.
-        let inv_d = inv_d_opt?;
+        let inv_d = inv_d_opt.ok_or(OpenReencryptedError::ZeroHash)?;
 
-        if &orig_pub_key * &(&s * &inv_d) != &(&e_prime * &h) + &v_prime {
-            return None;
+        if &orig_pub_key * &(&s * &inv_d) != &(e_prime * &h) + v_prime {
+            return Err(OpenReencryptedError::ValidationFailed);
         }
 
-        let shared_key = &(&e_prime + &v_prime) * &d;
-        Some(shared_key)
+        Ok(())
     }
+
+    /// Opens the capsule in presence of possibly corrupted capsule fragments
+    /// by searching for a subset of `threshold` of them that passes the validation.
+    /// Returns the shared key and the IDs of the fragments inconsistent with it.
+    ///
+    /// The number of tried subsets grows combinatorially with the number of corrupted fragments,
+    /// so verifying the fragments beforehand is preferable when the verifying keys are available.
+    pub(crate) fn open_reencrypted_robust(
+        &self,
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        cfrags: &[CapsuleFrag],
+    ) -> Result<(CurvePoint, Vec<KeyFragID>), OpenReencryptedError> {
+        if cfrags.is_empty() {
+            return Err(OpenReencryptedError::NoCapsuleFrags);
+        }
+
+        // Corrupted fragments can report an arbitrary threshold,
+        // so we try every value that was reported.
+        let mut thresholds = Vec::<usize>::new();
+        for cfrag in cfrags {
+            if !thresholds.contains(&cfrag.threshold()) {
+                thresholds.push(cfrag.threshold());
+            }
+        }
+        thresholds.sort_unstable();
+
+        for &threshold in &thresholds {
+            if threshold == 0 || threshold > cfrags.len() {
+                continue;
+            }
+
+            let mut indices: Vec<usize> = (0..threshold).collect();
+            loop {
+                let subset: Vec<CapsuleFrag> = indices.iter().map(|&i| cfrags[i].clone()).collect();
+
+                if let Ok(shared_key) = self.open_reencrypted(receiving_sk, delegating_pk, &subset)
+                {
+                    let faulty = self.find_faulty_cfrags(
+                        receiving_sk,
+                        delegating_pk,
+                        cfrags,
+                        &indices,
+                        &shared_key,
+                    );
+                    return Ok((shared_key, faulty));
+                }
+
+                if !next_combination(&mut indices, cfrags.len()) {
+                    break;
+                }
+            }
+        }
+
+        // `thresholds` is sorted and non-empty at this point.
+        if thresholds[0] > cfrags.len() {
+            Err(OpenReencryptedError::NotEnoughCapsuleFrags {
+                threshold: thresholds[0],
+                received: cfrags.len(),
+            })
+        } else {
+            Err(OpenReencryptedError::ValidationFailed)
+        }
+    }
+
+    /// Checks every fragment outside of a subset known to produce the correct shared key.
+    /// Replacing one element of the subset with a correct fragment
+    /// must lead to the same shared key.
+    fn find_faulty_cfrags(
+        &self,
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        cfrags: &[CapsuleFrag],
+        subset_indices: &[usize],
+        shared_key: &CurvePoint,
+    ) -> Vec<KeyFragID> {
+        let subset: Vec<&CapsuleFrag> = subset_indices.iter().map(|&i| &cfrags[i]).collect();
+        let base: Vec<CapsuleFrag> = subset[..subset.len() - 1]
+            .iter()
+            .map(|&cfrag| cfrag.clone())
+            .collect();
+
+        let mut faulty = Vec::<KeyFragID>::new();
+        for (i, cfrag) in cfrags.iter().enumerate() {
+            if subset_indices.contains(&i) {
+                continue;
+            }
+
+            let same_kfrag = subset.iter().find(|other| other.kfrag_id == cfrag.kfrag_id);
+            let consistent = match same_kfrag {
+                // Reencryption with the same key fragment must produce the same points
+                // (the proof is randomized, so it may differ).
+                Some(other) => {
+                    other.point_e1 == cfrag.point_e1
+                        && other.point_v1 == cfrag.point_v1
+                        && other.precursor == cfrag.precursor
+                        && other.threshold() == cfrag.threshold()
+                }
+                None => {
+                    let mut candidate = base.clone();
+                    candidate.push(cfrag.clone());
+                    match self.open_reencrypted(receiving_sk, delegating_pk, &candidate) {
+                        Ok(key) => &key == shared_key,
+                        Err(_) => false,
+                    }
+                }
+            };
+
+            if !consistent {
+                faulty.push(cfrag.kfrag_id);
+            }
+        }
+        faulty
+    }
+}
+
+/// Advances `indices` (a strictly increasing sequence of indices less than `n`)
+/// to the next combination in lexicographic order.
+/// Returns `false` if `indices` was the last combination.
+fn next_combination(indices: &mut [usize], n: usize) -> bool {
+    let k = indices.len();
+    for i in (0..k).rev() {
+        if indices[i] < n - k + i {
+            indices[i] += 1;
+            for j in i + 1..k {
+                indices[j] = indices[j - 1] + 1;
+            }
+            return true;
+        }
+    }
+    false
 }
 
-fn lambda_coeff(xs: &[CurveScalar], i: usize) -> Option<CurveScalar> {
+pub(crate) fn lambda_coeff(xs: &[CurveScalar], i: usize) -> Option<CurveScalar> {
     let mut res = CurveScalar::one();
     for j in 0..xs.len() {
         if j != i {
@@ -186,7 +480,7 @@ mod tests {
 
     use alloc::vec::Vec;
 
-    use super::Capsule;
+    use super::{Capsule, OpenReencryptedError};
     use crate::{
         encrypt, generate_kfrags, reencrypt, CapsuleFrag, Parameters, PublicKey, SecretKey,
         SerializableToArray,
@@ -243,9 +537,26 @@ mod tests {
         assert_eq!(key_seed, key_seed_reenc);
 
         // Empty cfrag vector
-        assert!(capsule
-            .open_reencrypted(&receiving_sk, &delegating_pk, &[])
-            .is_none());
+        assert_eq!(
+            capsule.open_reencrypted(&receiving_sk, &delegating_pk, &[]),
+            Err(OpenReencryptedError::NoCapsuleFrags)
+        );
+
+        // Not enough cfrags
+        assert_eq!(
+            capsule.open_reencrypted(&receiving_sk, &delegating_pk, &cfrags[0..1]),
+            Err(OpenReencryptedError::NotEnoughCapsuleFrags {
+                threshold: 2,
+                received: 1
+            })
+        );
+
+        // Repeating cfrags
+        let repeating_cfrags = [cfrags[0].clone(), cfrags[0].clone()];
+        assert_eq!(
+            capsule.open_reencrypted(&receiving_sk, &delegating_pk, &repeating_cfrags),
+            Err(OpenReencryptedError::RepeatingCapsuleFrags)
+        );
 
         // Mismatched cfrags - each `generate_kfrags()` uses new randoms.
         let kfrags2 = generate_kfrags(
@@ -269,14 +580,16 @@ mod tests {
             .cloned()
             .chain(cfrags2[1..2].iter().cloned())
             .collect();
-        assert!(capsule
-            .open_reencrypted(&receiving_sk, &delegating_pk, &mismatched_cfrags)
-            .is_none());
+        assert_eq!(
+            capsule.open_reencrypted(&receiving_sk, &delegating_pk, &mismatched_cfrags),
+            Err(OpenReencryptedError::MismatchedCapsuleFrags)
+        );
 
         // Mismatched capsule
         let (capsule2, _key_seed) = Capsule::from_pubkey(&params, &delegating_pk);
-        assert!(capsule2
-            .open_reencrypted(&receiving_sk, &delegating_pk, &cfrags)
-            .is_none());
+        assert_eq!(
+            capsule2.open_reencrypted(&receiving_sk, &delegating_pk, &cfrags),
+            Err(OpenReencryptedError::ValidationFailed)
+        );
     }
 }
@@ -1,14 +1,16 @@
 use crate::capsule::Capsule;
 use crate::curve::{CurvePoint, CurveScalar};
-use crate::curve::{PublicKey, Signature};
-use crate::hashing::{ScalarDigest, SignatureDigest};
+use crate::curve::{PublicKey, SecretKey, Signature};
+use crate::hashing::{HashingMode, ScalarDigest, SignatureDigest};
 use crate::hashing_ds::hash_metadata;
-use crate::key_frag::{KeyFrag, KeyFragID};
+use crate::key_frag::{KeyFrag, KeyFragID, KeyFragSignature, SignatureScheme, ValidityPeriod};
+use crate::params::Parameters;
+use crate::reencrypted_capsule::ReencryptedCapsule;
 use crate::traits::SerializableToArray;
 
 use generic_array::sequence::Concat;
 use generic_array::GenericArray;
-use typenum::{op, U32};
+use typenum::{op, U1, U32};
 
 // The compiler will ensure that's the array length we are getting from the hash function.
 // Hardcoding here for the purposes of the formal specification.
@@ -44,20 +46,39 @@ impl SerializableToArray for HashedMetadata {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct CapsuleFragProof {
-    point_e2: CurvePoint,
-    point_v2: CurvePoint,
-    kfrag_commitment: CurvePoint,
-    kfrag_pok: CurvePoint,
-    signature: CurveScalar,
-    kfrag_signature: Signature,
-    metadata: HashedMetadata,
+    pub(crate) point_e2: CurvePoint,
+    pub(crate) point_v2: CurvePoint,
+    pub(crate) kfrag_commitment: CurvePoint,
+    pub(crate) kfrag_pok: CurvePoint,
+    pub(crate) signature: CurveScalar,
+    pub(crate) kfrag_signature: KeyFragSignature,
+    pub(crate) metadata: HashedMetadata,
+    pub(crate) threshold: u32,
+    pub(crate) num_kfrags: u32,
+    pub(crate) validity: ValidityPeriod,
+    pub(crate) hashing_mode: HashingMode,
+    pub(crate) public_share_indices: bool,
 }
 
 type PointSize = <CurvePoint as SerializableToArray>::Size;
 type ScalarSize = <CurveScalar as SerializableToArray>::Size;
 type SignatureSize = <Signature as SerializableToArray>::Size;
-type CapsuleFragProofSize =
-    op!(PointSize + PointSize + PointSize + PointSize + ScalarSize + SignatureSize + ScalarSize);
+type KeyFragSignatureSize = <KeyFragSignature as SerializableToArray>::Size;
+type U32Size = <u32 as SerializableToArray>::Size;
+type ValidityPeriodSize = <ValidityPeriod as SerializableToArray>::Size;
+type HashingModeSize = <HashingMode as SerializableToArray>::Size;
+type CapsuleFragProofSize = op!(PointSize
+    + PointSize
+    + PointSize
+    + PointSize
+    + ScalarSize
+    + KeyFragSignatureSize
+    + ScalarSize
+    + U32Size
+    + U32Size
+    + ValidityPeriodSize
+    + HashingModeSize
+    + U1);
 
 impl SerializableToArray for CapsuleFragProof {
     type Size = CapsuleFragProofSize;
@@ -71,6 +92,11 @@ impl SerializableToArray for CapsuleFragProof {
             .concat(self.signature.to_array())
             .concat(self.kfrag_signature.to_array())
             .concat(self.metadata.to_array())
+            .concat(self.threshold.to_array())
+            .concat(self.num_kfrags.to_array())
+            .concat(self.validity.to_array())
+            .concat(self.hashing_mode.to_array())
+            .concat(self.public_share_indices.to_array())
     }
 
     fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
@@ -79,8 +105,16 @@ impl SerializableToArray for CapsuleFragProof {
         let (kfrag_commitment, rest) = CurvePoint::take(rest)?;
         let (kfrag_pok, rest) = CurvePoint::take(rest)?;
         let (signature, rest) = CurveScalar::take(rest)?;
-        let (kfrag_signature, rest) = Signature::take(rest)?;
-        let metadata = HashedMetadata::take_last(rest)?;
+        let (kfrag_signature, rest) = KeyFragSignature::take(rest)?;
+        let (metadata, rest) = HashedMetadata::take(rest)?;
+        let (threshold, rest) = u32::take(rest)?;
+        let (num_kfrags, rest) = u32::take(rest)?;
+        let (validity, rest) = ValidityPeriod::take(rest)?;
+        let (hashing_mode, rest) = HashingMode::take(rest)?;
+        let public_share_indices = bool::take_last(rest)?;
+        if !kfrag_signature.matches_hashing_mode(hashing_mode) {
+            return None;
+        }
         Some(Self {
             point_e2,
             point_v2,
@@ -89,6 +123,11 @@ impl SerializableToArray for CapsuleFragProof {
             signature,
             kfrag_signature,
             metadata,
+            threshold,
+            num_kfrags,
+            validity,
+            hashing_mode,
+            public_share_indices,
         })
     }
 }
@@ -96,21 +135,21 @@ impl SerializableToArray for CapsuleFragProof {
 impl CapsuleFragProof {
     #[allow(clippy::many_single_char_names)]
     fn from_kfrag_and_cfrag(
-        capsule: &Capsule,
+        params: &Parameters,
+        point_e: &CurvePoint,
+        point_v: &CurvePoint,
         kfrag: &KeyFrag,
         cfrag_e1: &CurvePoint,
         cfrag_v1: &CurvePoint,
         metadata: &HashedMetadata,
     ) -> Self {
-        let params = capsule.params;
-
         let rk = kfrag.key;
         let t = CurveScalar::random_nonzero();
 
         // Here are the formulaic constituents shared with `CapsuleFrag::verify()`.
 
-        let e = capsule.point_e;
-        let v = capsule.point_v;
+        let e = *point_e;
+        let v = *point_v;
 
         let e1 = cfrag_e1;
         let v1 = cfrag_v1;
@@ -122,7 +161,7 @@ impl CapsuleFragProof {
         let v2 = &v * &t;
         let u2 = &u * &t;
 
-        let h = ScalarDigest::new()
+        let h = ScalarDigest::new_with_mode(kfrag.proof.hashing_mode)
             .chain_points(&[e, *e1, e2, v, *v1, v2, u, u1, u2])
             .chain_bytes(metadata)
             .finalize();
@@ -139,6 +178,11 @@ impl CapsuleFragProof {
             signature: z3,
             kfrag_signature: kfrag.proof.signature_for_bob(),
             metadata: *metadata,
+            threshold: kfrag.proof.threshold,
+            num_kfrags: kfrag.proof.num_kfrags,
+            validity: kfrag.proof.validity,
+            hashing_mode: kfrag.proof.hashing_mode,
+            public_share_indices: kfrag.proof.public_share_indices,
         }
     }
 }
@@ -188,12 +232,44 @@ impl CapsuleFrag {
         capsule: &Capsule,
         kfrag: &KeyFrag,
         maybe_metadata: Option<&[u8]>,
+    ) -> Self {
+        Self::reencrypted_points(
+            &capsule.params,
+            &capsule.point_e,
+            &capsule.point_v,
+            kfrag,
+            maybe_metadata,
+        )
+    }
+
+    pub(crate) fn reencrypted_redelegated(
+        capsule: &ReencryptedCapsule,
+        kfrag: &KeyFrag,
+        maybe_metadata: Option<&[u8]>,
+    ) -> Self {
+        Self::reencrypted_points(
+            &capsule.capsule.params,
+            &capsule.point_e_prime,
+            &capsule.point_v_prime,
+            kfrag,
+            maybe_metadata,
+        )
+    }
+
+    fn reencrypted_points(
+        params: &Parameters,
+        point_e: &CurvePoint,
+        point_v: &CurvePoint,
+        kfrag: &KeyFrag,
+        maybe_metadata: Option<&[u8]>,
     ) -> Self {
         let rk = kfrag.key;
-        let e1 = &capsule.point_e * &rk;
-        let v1 = &capsule.point_v * &rk;
+        let e1 = point_e * &rk;
+        let v1 = point_v * &rk;
         let metadata = HashedMetadata::new(maybe_metadata);
-        let proof = CapsuleFragProof::from_kfrag_and_cfrag(&capsule, &kfrag, &e1, &v1, &metadata);
+        let proof = CapsuleFragProof::from_kfrag_and_cfrag(
+            params, point_e, point_v, kfrag, &e1, &v1, &metadata,
+        );
 
         Self {
             point_e1: e1,
@@ -213,13 +289,69 @@ impl CapsuleFrag {
         receiving_pk: &PublicKey,
         signing_pk: &PublicKey,
     ) -> bool {
-        let params = capsule.params;
+        self.verify_points(
+            &capsule.params,
+            &capsule.point_e,
+            &capsule.point_v,
+            delegating_pk,
+            receiving_pk,
+            signing_pk,
+        )
+    }
 
-        // Here are the formulaic constituents shared with
-        // `CapsuleFragProof::from_kfrag_and_cfrag`.
+    /// Verifies the integrity of the capsule fragment (see [`verify()`](`Self::verify()`)),
+    /// and checks that it was created with the given metadata
+    /// (see [`reencrypt()`](`crate::reencrypt()`)).
+    ///
+    /// The metadata is a part of the proof of correct reencryption,
+    /// so a successful check shows the proxy reencrypted the capsule
+    /// under the conditions the metadata describes.
+    pub fn verify_with_metadata(
+        &self,
+        capsule: &Capsule,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        signing_pk: &PublicKey,
+        expected_metadata: Option<&[u8]>,
+    ) -> bool {
+        let correct_metadata = self.proof.metadata == HashedMetadata::new(expected_metadata);
+        correct_metadata & self.verify(capsule, delegating_pk, receiving_pk, signing_pk)
+    }
+
+    /// Verifies the integrity of a capsule fragment created by
+    /// [`reencrypt_redelegated()`](`crate::reencrypt_redelegated()`).
+    ///
+    /// `delegating_pk` is the public key of the re-delegating party
+    /// (the one that called [`generate_redelegation_kfrags()`](`crate::generate_redelegation_kfrags()`)),
+    /// and `receiving_pk` is the public key of the new receiving party.
+    pub fn verify_redelegated(
+        &self,
+        capsule: &ReencryptedCapsule,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        signing_pk: &PublicKey,
+    ) -> bool {
+        self.verify_points(
+            &capsule.capsule.params,
+            &capsule.point_e_prime,
+            &capsule.point_v_prime,
+            delegating_pk,
+            receiving_pk,
+            signing_pk,
+        )
+    }
 
-        let e = capsule.point_e;
-        let v = capsule.point_v;
+    fn verify_points(
+        &self,
+        params: &Parameters,
+        point_e: &CurvePoint,
+        point_v: &CurvePoint,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        signing_pk: &PublicKey,
+    ) -> bool {
+        let e = *point_e;
+        let v = *point_v;
 
         let e1 = self.point_e1;
         let v1 = self.point_v1;
@@ -231,23 +363,12 @@ impl CapsuleFrag {
         let v2 = self.proof.point_v2;
         let u2 = self.proof.kfrag_pok;
 
-        let h = ScalarDigest::new()
-            .chain_points(&[e, e1, e2, v, v1, v2, u, u1, u2])
-            .chain_bytes(&self.proof.metadata)
-            .finalize();
+        let h = self.challenge(params, point_e, point_v);
 
         ///////
 
-        let precursor = self.precursor;
-        let kfrag_id = self.kfrag_id;
-
-        let valid_kfrag_signature = SignatureDigest::new()
-            .chain_bytes(&kfrag_id)
-            .chain_pubkey(delegating_pk)
-            .chain_pubkey(receiving_pk)
-            .chain_point(&u1)
-            .chain_point(&precursor)
-            .verify(signing_pk, &self.proof.kfrag_signature);
+        let valid_kfrag_signature =
+            self.valid_kfrag_signature(delegating_pk, receiving_pk, signing_pk);
 
         let z3 = self.proof.signature;
         let correct_reencryption_of_e = &e * &z3 == &e2 + &(&e1 * &h);
@@ -259,6 +380,156 @@ impl CapsuleFrag {
             & correct_reencryption_of_v
             & correct_rk_commitment
     }
+
+    /// Calculates the challenge scalar of the proof of correct reencryption.
+    pub(crate) fn challenge(
+        &self,
+        params: &Parameters,
+        point_e: &CurvePoint,
+        point_v: &CurvePoint,
+    ) -> CurveScalar {
+        // Here are the formulaic constituents shared with
+        // `CapsuleFragProof::from_kfrag_and_cfrag`.
+
+        let e = *point_e;
+        let v = *point_v;
+
+        let e1 = self.point_e1;
+        let v1 = self.point_v1;
+
+        let u = params.u;
+        let u1 = self.proof.kfrag_commitment;
+
+        let e2 = self.proof.point_e2;
+        let v2 = self.proof.point_v2;
+        let u2 = self.proof.kfrag_pok;
+
+        ScalarDigest::new_with_mode(self.proof.hashing_mode)
+            .chain_points(&[e, e1, e2, v, v1, v2, u, u1, u2])
+            .chain_bytes(&self.proof.metadata)
+            .finalize()
+    }
+
+    /// Checks the signature of the key fragment this capsule fragment was created with.
+    pub(crate) fn valid_kfrag_signature(
+        &self,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        signing_pk: &PublicKey,
+    ) -> bool {
+        let mut digest = SignatureDigest::new_with_mode(self.proof.hashing_mode)
+            .chain_bytes(self.kfrag_id)
+            .chain_pubkey(delegating_pk)
+            .chain_pubkey(receiving_pk)
+            .chain_point(&self.proof.kfrag_commitment)
+            .chain_point(&self.precursor)
+            .chain_u32(self.proof.threshold)
+            .chain_u32(self.proof.num_kfrags)
+            .chain_bytes(self.proof.validity.to_array());
+        if self.proof.public_share_indices {
+            digest = digest.chain_bool(true);
+        }
+        self.proof.kfrag_signature.verify(digest, signing_pk)
+    }
+
+    /// Returns the hash of the metadata the capsule fragment was created with
+    /// (an absent metadata is hashed as an empty one).
+    pub fn hashed_metadata(&self) -> GenericArray<u8, HashedMetadataSize> {
+        self.proof.metadata.to_array()
+    }
+
+    /// Returns the identifier of the key fragment this capsule fragment was created with.
+    pub fn kfrag_id(&self) -> KeyFragID {
+        self.kfrag_id
+    }
+
+    /// Returns the number of capsule fragments necessary for decryption.
+    ///
+    /// The value is signed by the delegating party along with the key fragment,
+    /// so it can be trusted after a successful [`verify()`](`Self::verify()`).
+    pub fn threshold(&self) -> usize {
+        self.proof.threshold as usize
+    }
+
+    /// Returns the total number of key fragments issued for this delegation.
+    ///
+    /// The value is signed by the delegating party along with the key fragment,
+    /// so it can be trusted after a successful [`verify()`](`Self::verify()`).
+    pub fn num_kfrags(&self) -> usize {
+        self.proof.num_kfrags as usize
+    }
+
+    /// Returns the period the key fragment this capsule fragment was created with
+    /// could be used for reencryption in.
+    ///
+    /// The value is signed by the delegating party along with the key fragment,
+    /// so it can be trusted after a successful [`verify()`](`Self::verify()`).
+    /// The receiving party can compare it with the time it received the fragment at
+    /// to reject the ones produced outside of the period.
+    pub fn validity(&self) -> ValidityPeriod {
+        self.proof.validity
+    }
+
+    /// Returns the hashing mode of the proof of correct reencryption
+    /// and of the key fragment signature (see [`HashingMode`]).
+    pub fn hashing_mode(&self) -> HashingMode {
+        self.proof.hashing_mode
+    }
+
+    /// Returns the scheme of the key fragment signature.
+    pub fn signature_scheme(&self) -> SignatureScheme {
+        self.proof.kfrag_signature.scheme()
+    }
+}
+
+/// A [`CapsuleFrag`] signed by the proxy that created it.
+///
+/// The signature makes the proxy accountable for the fragment,
+/// complementing the proof of correct reencryption.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedCapsuleFrag {
+    pub(crate) cfrag: CapsuleFrag,
+    pub(crate) signature: Signature,
+}
+
+impl SerializableToArray for SignedCapsuleFrag {
+    type Size = op!(CapsuleFragSize + SignatureSize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.cfrag.to_array().concat(self.signature.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (cfrag, rest) = CapsuleFrag::take(*arr)?;
+        let signature = Signature::take_last(rest)?;
+        Some(Self { cfrag, signature })
+    }
+}
+
+impl SignedCapsuleFrag {
+    pub(crate) fn new(cfrag: CapsuleFrag, proxy_sk: &SecretKey) -> Self {
+        let signature = Self::digest(&cfrag).sign(proxy_sk);
+        Self { cfrag, signature }
+    }
+
+    fn digest(cfrag: &CapsuleFrag) -> SignatureDigest {
+        SignatureDigest::new()
+            .chain_bytes(cfrag.to_array())
+            .chain_bytes(cfrag.proof.metadata)
+    }
+
+    /// Verifies the proxy's signature of the capsule fragment.
+    ///
+    /// The fragment itself should be verified with [`CapsuleFrag::verify()`]
+    /// (or [`CapsuleFrag::verify_with_metadata()`]).
+    pub fn verify(&self, proxy_pk: &PublicKey) -> bool {
+        Self::digest(&self.cfrag).verify(proxy_pk, &self.signature)
+    }
+
+    /// Returns the signed capsule fragment.
+    pub fn cfrag(&self) -> &CapsuleFrag {
+        &self.cfrag
+    }
 }
 
 #[cfg(test)]
@@ -267,10 +538,10 @@ mod tests {
     use alloc::boxed::Box;
     use alloc::vec::Vec;
 
-    use super::CapsuleFrag;
+    use super::{CapsuleFrag, SignedCapsuleFrag};
     use crate::{
-        encrypt, generate_kfrags, reencrypt, Capsule, Parameters, PublicKey, SecretKey,
-        SerializableToArray,
+        encrypt, generate_kfrags, reencrypt, reencrypt_signed, Capsule, Parameters, PublicKey,
+        SecretKey, SerializableToArray,
     };
 
     fn prepare_cfrags() -> (PublicKey, PublicKey, PublicKey, Capsule, Box<[CapsuleFrag]>) {
@@ -331,4 +602,113 @@ mod tests {
             &signing_pk,
         )));
     }
+
+    #[test]
+    fn test_verify_with_metadata() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let (capsule, _ciphertext) = encrypt(&params, &delegating_pk, b"peace at dawn").unwrap();
+
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+
+        let metadata = b"request #1";
+        let cfrag = reencrypt(&capsule, &kfrags[0], Some(metadata));
+        let cfrag_no_metadata = reencrypt(&capsule, &kfrags[0], None);
+
+        assert_ne!(cfrag.hashed_metadata(), cfrag_no_metadata.hashed_metadata());
+
+        assert!(cfrag.verify_with_metadata(
+            &capsule,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            Some(metadata)
+        ));
+        assert!(!cfrag.verify_with_metadata(
+            &capsule,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            Some(b"request #2")
+        ));
+        assert!(!cfrag.verify_with_metadata(
+            &capsule,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            None
+        ));
+        assert!(cfrag_no_metadata.verify_with_metadata(
+            &capsule,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            None
+        ));
+    }
+
+    #[test]
+    fn test_signed_cfrag() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let proxy_sk = SecretKey::random();
+        let proxy_pk = PublicKey::from_secret_key(&proxy_sk);
+
+        let (capsule, _ciphertext) = encrypt(&params, &delegating_pk, b"peace at dawn").unwrap();
+
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+
+        let metadata = b"request #1";
+        let signed_cfrag = reencrypt_signed(&capsule, &kfrags[0], Some(metadata), &proxy_sk);
+
+        let signed_cfrag_arr = signed_cfrag.to_array();
+        let signed_cfrag_back = SignedCapsuleFrag::from_array(&signed_cfrag_arr).unwrap();
+        assert_eq!(signed_cfrag, signed_cfrag_back);
+
+        assert!(signed_cfrag.verify(&proxy_pk));
+        assert!(!signed_cfrag.verify(&signing_pk));
+        assert!(signed_cfrag.cfrag().verify_with_metadata(
+            &capsule,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            Some(metadata)
+        ));
+    }
 }
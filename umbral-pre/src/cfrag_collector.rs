@@ -0,0 +1,232 @@
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::curve::{PublicKey, SecretKey};
+use crate::pre::{decrypt_reencrypted, ReencryptionError};
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Errors that can happen when adding a capsule fragment to a [`CfragCollector`].
+#[derive(Debug, PartialEq)]
+pub enum CollectCfragError {
+    /// The capsule fragment does not verify against the collector's capsule and keys.
+    VerificationFailed,
+    /// A capsule fragment created with the same key fragment was already collected.
+    RepeatingCapsuleFrag,
+    /// The capsule fragment originated from a different [`generate_kfrags`](crate::generate_kfrags)
+    /// call than the ones collected before.
+    MismatchedCapsuleFrag,
+}
+
+impl fmt::Display for CollectCfragError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::VerificationFailed => write!(f, "CapsuleFrag verification failed"),
+            Self::RepeatingCapsuleFrag => write!(f, "CapsuleFrag is already collected"),
+            Self::MismatchedCapsuleFrag => {
+                write!(
+                    f,
+                    "CapsuleFrag is inconsistent with the ones collected before"
+                )
+            }
+        }
+    }
+}
+
+/// An accumulator of capsule fragments for a single [`Capsule`],
+/// used by the receiving party while it waits for the proxies' responses.
+///
+/// Every added fragment is verified, so only the ones produced from valid key fragments
+/// for the given delegating, receiving and signing keys end up being used for decryption.
+#[derive(Clone, Debug)]
+pub struct CfragCollector {
+    capsule: Capsule,
+    decrypting_sk: SecretKey,
+    receiving_pk: PublicKey,
+    delegating_pk: PublicKey,
+    signing_pk: PublicKey,
+    cfrags: Vec<CapsuleFrag>,
+}
+
+impl CfragCollector {
+    /// Creates an empty collector.
+    ///
+    /// `decrypting_sk` is the secret key whose associated public key was used in
+    /// [`generate_kfrags()`](`crate::generate_kfrags()`),
+    /// `delegating_pk` is the public key of the encrypting party,
+    /// and `signing_pk` is the public key the key fragments were signed with.
+    pub fn new(
+        capsule: &Capsule,
+        decrypting_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        signing_pk: &PublicKey,
+    ) -> Self {
+        Self {
+            capsule: *capsule,
+            decrypting_sk: decrypting_sk.clone(),
+            receiving_pk: PublicKey::from_secret_key(decrypting_sk),
+            delegating_pk: *delegating_pk,
+            signing_pk: *signing_pk,
+            cfrags: Vec::new(),
+        }
+    }
+
+    /// Verifies the capsule fragment and adds it to the collection.
+    pub fn add_cfrag(&mut self, cfrag: &CapsuleFrag) -> Result<(), CollectCfragError> {
+        // Cheap consistency checks go first.
+        if let Some(first) = self.cfrags.first() {
+            if first.precursor != cfrag.precursor || first.threshold() != cfrag.threshold() {
+                return Err(CollectCfragError::MismatchedCapsuleFrag);
+            }
+        }
+
+        if self
+            .cfrags
+            .iter()
+            .any(|collected| collected.kfrag_id == cfrag.kfrag_id)
+        {
+            return Err(CollectCfragError::RepeatingCapsuleFrag);
+        }
+
+        if !cfrag.verify(
+            &self.capsule,
+            &self.delegating_pk,
+            &self.receiving_pk,
+            &self.signing_pk,
+        ) {
+            return Err(CollectCfragError::VerificationFailed);
+        }
+
+        self.cfrags.push(cfrag.clone());
+        Ok(())
+    }
+
+    /// Returns the number of capsule fragments collected so far.
+    pub fn received(&self) -> usize {
+        self.cfrags.len()
+    }
+
+    /// Returns the number of capsule fragments necessary for decryption,
+    /// or `None` if no fragments were collected yet.
+    pub fn threshold(&self) -> Option<usize> {
+        self.cfrags.first().map(|cfrag| cfrag.threshold())
+    }
+
+    /// Returns `true` if enough capsule fragments were collected to decrypt.
+    pub fn is_ready(&self) -> bool {
+        match self.threshold() {
+            Some(threshold) => self.received() >= threshold,
+            None => false,
+        }
+    }
+
+    /// Decrypts the ciphertext using the collected capsule fragments.
+    pub fn decrypt(&self, ciphertext: impl AsRef<[u8]>) -> Result<Box<[u8]>, ReencryptionError> {
+        decrypt_reencrypted(
+            &self.decrypting_sk,
+            &self.delegating_pk,
+            &self.capsule,
+            &self.cfrags,
+            ciphertext,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{CfragCollector, CollectCfragError};
+    use crate::{
+        encrypt, generate_kfrags, reencrypt, OpenReencryptedError, Parameters, PublicKey,
+        ReencryptionError, SecretKey,
+    };
+
+    #[test]
+    fn test_collect_and_decrypt() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+
+        let mut collector =
+            CfragCollector::new(&capsule, &receiving_sk, &delegating_pk, &signing_pk);
+        assert_eq!(collector.threshold(), None);
+        assert!(!collector.is_ready());
+
+        let cfrag0 = reencrypt(&capsule, &kfrags[0], None);
+        collector.add_cfrag(&cfrag0).unwrap();
+        assert_eq!(collector.received(), 1);
+        assert_eq!(collector.threshold(), Some(2));
+        assert!(!collector.is_ready());
+        assert_eq!(
+            collector.decrypt(&ciphertext),
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::NotEnoughCapsuleFrags {
+                    threshold: 2,
+                    received: 1
+                }
+            ))
+        );
+
+        // The same kfrag used twice
+        let cfrag0_again = reencrypt(&capsule, &kfrags[0], None);
+        assert_eq!(
+            collector.add_cfrag(&cfrag0_again),
+            Err(CollectCfragError::RepeatingCapsuleFrag)
+        );
+
+        // A cfrag from a different delegation
+        let other_kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        let other_cfrag = reencrypt(&capsule, &other_kfrags[1], None);
+        assert_eq!(
+            collector.add_cfrag(&other_cfrag),
+            Err(CollectCfragError::MismatchedCapsuleFrag)
+        );
+
+        // A cfrag for a different capsule
+        let (other_capsule, _ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+        let wrong_cfrag = reencrypt(&other_capsule, &kfrags[1], None);
+        assert_eq!(
+            collector.add_cfrag(&wrong_cfrag),
+            Err(CollectCfragError::VerificationFailed)
+        );
+        assert_eq!(collector.received(), 1);
+
+        let cfrag1 = reencrypt(&capsule, &kfrags[1], None);
+        collector.add_cfrag(&cfrag1).unwrap();
+        assert!(collector.is_ready());
+
+        let plaintext_bob = collector.decrypt(&ciphertext).unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+}
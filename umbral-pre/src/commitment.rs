@@ -0,0 +1,303 @@
+use crate::curve::{CurvePoint, CurveScalar, PublicKey, SecretKey, Signature};
+use crate::hashing::SignatureDigest;
+use crate::key_frag::{poly_eval, KeyFrag};
+use crate::traits::SerializableToArray;
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use typenum::Unsigned;
+
+/// A signed Feldman commitment to the polynomial the [`KeyFrag`] objects
+/// of a single delegation are the shares of.
+///
+/// It is meant to be published along with the delegation,
+/// so that every proxy can check its own fragment with
+/// [`KeyFrag::verify_against_commitment()`].
+///
+/// Since its size depends on the threshold, it is serialized with
+/// [`to_bytes()`](`Self::to_bytes()`) instead of [`SerializableToArray`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolynomialCommitment {
+    pub(crate) precursor: CurvePoint,
+    num_kfrags: u32,
+    // `u` multiplied by each of the coefficients, starting from the constant one.
+    coefficients: Box<[CurvePoint]>,
+    signature: Signature,
+}
+
+type PointSize = <CurvePoint as SerializableToArray>::Size;
+type SignatureSize = <Signature as SerializableToArray>::Size;
+type U32Size = <u32 as SerializableToArray>::Size;
+
+impl PolynomialCommitment {
+    /// Creates a commitment from the fragments produced by a single
+    /// [`generate_kfrags()`](`crate::generate_kfrags()`) call
+    /// (or a single [`refresh_kfrags()`](`crate::refresh_kfrags()`) call),
+    /// signing it with `signing_sk`.
+    ///
+    /// At least `threshold` fragments must be given,
+    /// and they must be created with public share indices
+    /// (see [`KeyFragOptions::public_share_indices()`](`crate::KeyFragOptions::public_share_indices()`)).
+    /// Returns `None` if there are not enough of them, they have private share indices,
+    /// or they are not consistent with each other.
+    pub fn new(signing_sk: &SecretKey, kfrags: &[KeyFrag]) -> Option<Self> {
+        let first = kfrags.first()?;
+        let threshold = first.threshold();
+
+        if threshold == 0
+            || kfrags.len() < threshold
+            || !kfrags.iter().all(|kfrag| {
+                kfrag.precursor == first.precursor
+                    && kfrag.params == first.params
+                    && kfrag.threshold() == threshold
+                    && kfrag.num_kfrags() == first.num_kfrags()
+            })
+        {
+            return None;
+        }
+
+        let share_indices = kfrags
+            .iter()
+            .map(|kfrag| kfrag.public_share_index())
+            .collect::<Option<Vec<_>>>()?;
+
+        let coefficients = interpolate(&kfrags[..threshold], &share_indices[..threshold])?;
+
+        // Make sure the rest of the fragments lie on the same polynomial.
+        if !kfrags
+            .iter()
+            .zip(share_indices.iter())
+            .all(|(kfrag, share_index)| poly_eval(&coefficients, share_index) == kfrag.key)
+        {
+            return None;
+        }
+
+        let u = first.params.u;
+        let coefficients: Vec<CurvePoint> = coefficients.iter().map(|coeff| &u * coeff).collect();
+        let num_kfrags = first.num_kfrags() as u32;
+
+        let signature = Self::digest(&first.precursor, num_kfrags, &coefficients).sign(signing_sk);
+
+        Some(Self {
+            precursor: first.precursor,
+            num_kfrags,
+            coefficients: coefficients.into_boxed_slice(),
+            signature,
+        })
+    }
+
+    fn digest(
+        precursor: &CurvePoint,
+        num_kfrags: u32,
+        coefficients: &[CurvePoint],
+    ) -> SignatureDigest {
+        let mut digest = SignatureDigest::new()
+            .chain_point(precursor)
+            .chain_u32(coefficients.len() as u32)
+            .chain_u32(num_kfrags);
+        for coeff in coefficients {
+            digest = digest.chain_point(coeff);
+        }
+        digest
+    }
+
+    /// Verifies the signature of the commitment.
+    pub fn verify(&self, signing_pk: &PublicKey) -> bool {
+        Self::digest(&self.precursor, self.num_kfrags, &self.coefficients)
+            .verify(signing_pk, &self.signature)
+    }
+
+    /// Returns the number of fragments necessary for decryption
+    /// (that is, the number of coefficients of the committed polynomial).
+    pub fn threshold(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    /// Returns the total number of fragments issued for the delegation.
+    pub fn num_kfrags(&self) -> usize {
+        self.num_kfrags as usize
+    }
+
+    /// Evaluates the committed polynomial "in the exponent" at `x`.
+    pub(crate) fn evaluate(&self, x: &CurveScalar) -> CurvePoint {
+        let mut result = self.coefficients[self.coefficients.len() - 1];
+        for coeff in self.coefficients[..self.coefficients.len() - 1]
+            .iter()
+            .rev()
+        {
+            result = &(&result * x) + coeff;
+        }
+        result
+    }
+
+    /// Produces a byte array with the commitment's contents.
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        let mut result = Vec::<u8>::new();
+        result.extend_from_slice(&self.precursor.to_array());
+        result.extend_from_slice(&self.num_kfrags.to_array());
+        result.extend_from_slice(&self.signature.to_array());
+        for coeff in self.coefficients.iter() {
+            result.extend_from_slice(&coeff.to_array());
+        }
+        result.into_boxed_slice()
+    }
+
+    /// Attempts to produce the commitment back from the serialized form.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Option<Self> {
+        let bytes = bytes.as_ref();
+        let point_size = PointSize::to_usize();
+        let header_size = point_size + U32Size::to_usize() + SignatureSize::to_usize();
+
+        if bytes.len() <= header_size || (bytes.len() - header_size) % point_size != 0 {
+            return None;
+        }
+
+        let (header, rest) = bytes.split_at(header_size);
+        let (precursor_bytes, header) = header.split_at(point_size);
+        let (num_kfrags_bytes, signature_bytes) = header.split_at(U32Size::to_usize());
+
+        let precursor = CurvePoint::from_bytes(precursor_bytes)?;
+        let num_kfrags = u32::from_bytes(num_kfrags_bytes)?;
+        let signature = Signature::from_bytes(signature_bytes)?;
+        let coefficients = rest
+            .chunks(point_size)
+            .map(CurvePoint::from_bytes)
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            precursor,
+            num_kfrags,
+            coefficients: coefficients.into_boxed_slice(),
+            signature,
+        })
+    }
+}
+
+/// Recovers the coefficients of the polynomial of degree `kfrags.len() - 1`
+/// from its values at the share indices of the fragments.
+fn interpolate(kfrags: &[KeyFrag], share_indices: &[CurveScalar]) -> Option<Vec<CurveScalar>> {
+    let zero = CurveScalar::default();
+    let mut result = vec![zero; kfrags.len()];
+
+    for (i, kfrag) in kfrags.iter().enumerate() {
+        // The coefficients of the Lagrange basis polynomial for the i-th point
+        let mut basis = vec![zero; kfrags.len()];
+        basis[0] = CurveScalar::one();
+        let mut denominator = CurveScalar::one();
+        let mut degree = 0;
+
+        for (m, other_index) in share_indices.iter().enumerate() {
+            if m == i {
+                continue;
+            }
+
+            // Multiply the basis polynomial by `(X - x_m)`
+            degree += 1;
+            for j in (1..=degree).rev() {
+                basis[j] = &basis[j - 1] - &(&basis[j] * other_index);
+            }
+            basis[0] = &zero - &(&basis[0] * other_index);
+
+            denominator = &denominator * &(&share_indices[i] - other_index);
+        }
+
+        // Repeating share indices mean repeating fragments, which give no information.
+        let inv_denominator: Option<CurveScalar> = denominator.invert().into();
+        let factor = &kfrag.key * &inv_denominator?;
+
+        for (res, coeff) in result.iter_mut().zip(basis.iter()) {
+            *res = &*res + &(coeff * &factor);
+        }
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::PolynomialCommitment;
+    use crate::{
+        generate_kfrags_with_options, refresh_kfrags, KeyFragOptions, Parameters, PublicKey,
+        SecretKey,
+    };
+
+    #[test]
+    fn test_verify_against_commitment() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let (kfrags, refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            3,
+            5,
+            true,
+            true,
+            &KeyFragOptions::new().public_share_indices(true),
+        );
+
+        let commitment = PolynomialCommitment::new(&signing_sk, &kfrags).unwrap();
+        assert_eq!(commitment.threshold(), 3);
+        assert_eq!(commitment.num_kfrags(), 5);
+        assert!(commitment.verify(&signing_pk));
+        assert!(!commitment.verify(&receiving_pk));
+
+        let commitment_bytes = commitment.to_bytes();
+        let commitment_back = PolynomialCommitment::from_bytes(&commitment_bytes).unwrap();
+        assert_eq!(commitment, commitment_back);
+        assert!(PolynomialCommitment::from_bytes(&commitment_bytes[1..]).is_none());
+
+        assert!(kfrags
+            .iter()
+            .all(|kfrag| kfrag.verify_against_commitment(&commitment, &signing_pk)));
+        assert!(!kfrags[0].verify_against_commitment(&commitment, &receiving_pk));
+
+        // A properly signed share that does not lie on the committed polynomial
+        // (refreshing a single fragment moves it to a different one)
+        let mut bad_kfrags = kfrags.clone();
+        bad_kfrags[1] =
+            refresh_kfrags(&refresh_key, &signing_sk, &kfrags[1..2]).unwrap()[0].clone();
+        assert!(bad_kfrags[1].verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk)));
+        assert!(!bad_kfrags[1].verify_against_commitment(&commitment, &signing_pk));
+        assert!(PolynomialCommitment::new(&signing_sk, &bad_kfrags).is_none());
+
+        // Not enough fragments to interpolate
+        assert!(PolynomialCommitment::new(&signing_sk, &kfrags[..2]).is_none());
+
+        // Refreshed fragments need a new commitment
+        let new_kfrags = refresh_kfrags(&refresh_key, &signing_sk, &kfrags).unwrap();
+        assert!(!new_kfrags[0].verify_against_commitment(&commitment, &signing_pk));
+        let new_commitment = PolynomialCommitment::new(&signing_sk, &new_kfrags[2..]).unwrap();
+        assert!(new_kfrags
+            .iter()
+            .all(|kfrag| kfrag.verify_against_commitment(&new_commitment, &signing_pk)));
+
+        // The fragments with private share indices cannot be checked against a commitment
+        let (private_kfrags, _refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            3,
+            5,
+            true,
+            true,
+            &KeyFragOptions::new(),
+        );
+        assert!(PolynomialCommitment::new(&signing_sk, &private_kfrags).is_none());
+        assert!(!private_kfrags[0].verify_against_commitment(&commitment, &signing_pk));
+    }
+}
@@ -5,7 +5,7 @@
 use core::default::Default;
 use core::ops::{Add, Mul, Sub};
 use digest::{BlockInput, Digest, FixedOutput, Reset, Update};
-use ecdsa::{Signature as BackendSignature, SignatureSize, SigningKey, VerifyingKey};
+use ecdsa::Signature as BackendSignature;
 use elliptic_curve::ff::PrimeField;
 use elliptic_curve::scalar::NonZeroScalar;
 use elliptic_curve::sec1::{CompressedPointSize, EncodedPoint, FromEncodedPoint, ToEncodedPoint};
@@ -13,8 +13,9 @@ use elliptic_curve::{
     Curve, FromDigest, ProjectiveArithmetic, PublicKey as BackendPublicKey, Scalar,
     SecretKey as BackendSecretKey,
 };
-use generic_array::typenum::U32;
+use generic_array::typenum::{U32, U64, U65};
 use generic_array::GenericArray;
+use k256::ecdsa::{recoverable, SigningKey, VerifyingKey};
 use k256::Secp256k1;
 use rand_core::OsRng;
 use signature::{DigestVerifier, RandomizedDigestSigner, Signature as SignatureTrait};
@@ -47,6 +48,10 @@ impl CurveScalar {
         Self(BackendScalar::one())
     }
 
+    pub(crate) fn from_u32(val: u32) -> Self {
+        Self(BackendScalar::from(val as u64))
+    }
+
     pub(crate) fn is_zero(&self) -> bool {
         self.0.is_zero().into()
     }
@@ -156,11 +161,46 @@ impl SerializableToArray for CurvePoint {
     }
 }
 
+/// A curve point serialized as uncompressed affine coordinates (`x` followed by `y`,
+/// without the SEC1 tag byte), for consumers that cannot decompress points cheaply.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UncompressedPoint(pub(crate) CurvePoint);
+
+impl SerializableToArray for UncompressedPoint {
+    type Size = U64;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        // The identity has no affine coordinates, and is encoded as all zeros
+        // (the same way the EVM precompiles do it).
+        // Since `(0, 0)` does not lie on the curve, this encoding is unambiguous.
+        let ep = self.0 .0.to_affine().to_encoded_point(false);
+        let mut arr = GenericArray::<u8, Self::Size>::default();
+        if ep.as_bytes().len() == 65 {
+            arr.copy_from_slice(&ep.as_bytes()[1..]);
+        }
+        arr
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        if arr.iter().all(|byte| *byte == 0) {
+            return Some(Self(CurvePoint::identity()));
+        }
+
+        let mut bytes = [0u8; 65];
+        // SEC1 tag of an uncompressed point
+        bytes[0] = 4;
+        bytes[1..].copy_from_slice(arr.as_slice());
+        let ep = EncodedPoint::<CurveType>::from_bytes(&bytes[..]).ok()?;
+        let cp_opt: Option<BackendPoint> = BackendPoint::from_encoded_point(&ep);
+        cp_opt.map(|cp| Self(CurvePoint(cp)))
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Signature(BackendSignature<CurveType>);
 
 impl SerializableToArray for Signature {
-    type Size = SignatureSize<CurveType>;
+    type Size = U64;
 
     fn to_array(&self) -> GenericArray<u8, Self::Size> {
         *GenericArray::<u8, Self::Size>::from_slice(self.0.as_bytes())
@@ -173,6 +213,28 @@ impl SerializableToArray for Signature {
     }
 }
 
+/// A recoverable ECDSA signature, serialized as `r`, `s` and the recovery ID `v` (0 or 1),
+/// the same way Ethereum signatures are (save for the offset of 27 in `v`).
+///
+/// Only used along with [`HashingMode::Keccak256`](`crate::HashingMode::Keccak256`),
+/// where it allows the signature to be checked with `ecrecover` on the EVM.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecoverableSignature(recoverable::Signature);
+
+impl SerializableToArray for RecoverableSignature {
+    type Size = U65;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        *GenericArray::<u8, Self::Size>::from_slice(self.0.as_bytes())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        recoverable::Signature::from_bytes(arr.as_slice())
+            .ok()
+            .map(Self)
+    }
+}
+
 /// A secret key.
 #[derive(Clone, Debug)]
 pub struct SecretKey(BackendSecretKey<CurveType>);
@@ -201,14 +263,29 @@ impl SecretKey {
         CurveScalar(**self.0.secret_scalar())
     }
 
+    /// Creates a secret key from a non-zero scalar.
+    pub(crate) fn from_scalar(scalar: &CurveScalar) -> Self {
+        // The scalar is guaranteed to be non-zero by the caller.
+        Self::from_array(&scalar.to_array()).unwrap()
+    }
+
     /// Signs a message using the default RNG.
     pub(crate) fn sign_digest(
         &self,
         digest: impl BlockInput + FixedOutput<OutputSize = U32> + Clone + Default + Reset + Update,
     ) -> Signature {
-        let signer = SigningKey::<CurveType>::from(self.0.clone());
+        let signer = SigningKey::from(&self.0);
         Signature(signer.sign_digest_with_rng(OsRng, digest))
     }
+
+    /// Signs a message using the default RNG, producing a recoverable signature.
+    pub(crate) fn sign_digest_recoverable(
+        &self,
+        digest: impl BlockInput + FixedOutput<OutputSize = U32> + Clone + Default + Reset + Update,
+    ) -> RecoverableSignature {
+        let signer = SigningKey::from(&self.0);
+        RecoverableSignature(signer.sign_digest_with_rng(OsRng, digest))
+    }
 }
 
 impl SerializableToArray for SecretKey {
@@ -235,6 +312,12 @@ impl PublicKey {
         Self(secret_key.0.public_key())
     }
 
+    /// Creates a public key from a curve point, failing if it is the identity.
+    pub(crate) fn from_point(point: &CurvePoint) -> Option<Self> {
+        let backend_pk = BackendPublicKey::<CurveType>::from_affine(point.0.to_affine()).ok()?;
+        Some(Self(backend_pk))
+    }
+
     /// Returns the underlying curve point of the public key.
     pub(crate) fn to_point(&self) -> CurvePoint {
         CurvePoint(self.0.to_projective())
@@ -246,9 +329,24 @@ impl PublicKey {
         digest: impl Digest<OutputSize = U32>,
         signature: &Signature,
     ) -> bool {
-        let verifier = VerifyingKey::from(&self.0);
+        let verifier = VerifyingKey::from(self.0.as_affine());
         verifier.verify_digest(digest, &signature.0).is_ok()
     }
+
+    /// Verifies the recoverable signature, including its recovery ID.
+    pub(crate) fn verify_digest_recoverable(
+        &self,
+        digest: impl Digest<OutputSize = U32> + Clone,
+        signature: &RecoverableSignature,
+    ) -> bool {
+        let verifier = VerifyingKey::from(self.0.as_affine());
+        let recovers_to_key = signature
+            .0
+            .recover_verify_key_from_digest(digest.clone())
+            .map_or(false, |recovered| recovered == verifier);
+        let backend_signature: BackendSignature<CurveType> = signature.0.into();
+        recovers_to_key & verifier.verify_digest(digest, &backend_signature).is_ok()
+    }
 }
 
 impl SerializableToArray for PublicKey {
@@ -260,8 +358,7 @@ impl SerializableToArray for PublicKey {
 
     fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
         let cp = CurvePoint::from_array(&arr)?;
-        let backend_pk = BackendPublicKey::<CurveType>::from_affine(cp.0.to_affine()).ok()?;
-        Some(Self(backend_pk))
+        Self::from_point(&cp)
     }
 }
 
@@ -271,7 +368,7 @@ mod tests {
     use sha2::Sha256;
     use signature::digest::Digest;
 
-    use super::{PublicKey, SecretKey};
+    use super::{CurvePoint, PublicKey, SecretKey, UncompressedPoint};
     use crate::SerializableToArray;
 
     #[test]
@@ -291,6 +388,16 @@ mod tests {
         assert_eq!(pk, pk_back);
     }
 
+    #[test]
+    fn test_serialize_uncompressed_point() {
+        let point = PublicKey::from_secret_key(&SecretKey::random()).to_point();
+        for point in [point, CurvePoint::identity()].iter() {
+            let point_arr = UncompressedPoint(*point).to_array();
+            let point_back = UncompressedPoint::from_array(&point_arr).unwrap();
+            assert_eq!(point, &point_back.0);
+        }
+    }
+
     #[test]
     fn test_sign_and_verify() {
         let sk = SecretKey::random();
@@ -9,9 +9,14 @@ use rand_core::OsRng;
 use rand_core::RngCore;
 use sha2::Sha256;
 
-type KdfSize = <ChaCha20Poly1305 as NewAead>::KeySize;
-
-fn kdf(seed: &[u8], salt: Option<&[u8]>, info: Option<&[u8]>) -> GenericArray<u8, KdfSize> {
+pub(crate) type KdfSize = <ChaCha20Poly1305 as NewAead>::KeySize;
+pub(crate) type NonceSize = <ChaCha20Poly1305 as AeadInPlace>::NonceSize;
+
+pub(crate) fn kdf(
+    seed: &[u8],
+    salt: Option<&[u8]>,
+    info: Option<&[u8]>,
+) -> GenericArray<u8, KdfSize> {
     let hk = Hkdf::<Sha256>::new(salt, &seed);
 
     let mut okm = GenericArray::<u8, KdfSize>::default();
@@ -37,10 +42,19 @@ impl UmbralDEM {
     }
 
     pub fn encrypt(&self, data: &[u8], authenticated_data: &[u8]) -> Option<Box<[u8]>> {
-        type NonceSize = <ChaCha20Poly1305 as AeadInPlace>::NonceSize;
         let mut nonce = GenericArray::<u8, NonceSize>::default();
         OsRng.fill_bytes(&mut nonce);
-        let nonce = Nonce::from_slice(&nonce);
+        self.encrypt_with_nonce(data, authenticated_data, &nonce)
+    }
+
+    /// The nonce must never be reused with the same key.
+    pub fn encrypt_with_nonce(
+        &self,
+        data: &[u8],
+        authenticated_data: &[u8],
+        nonce: &GenericArray<u8, NonceSize>,
+    ) -> Option<Box<[u8]>> {
+        let nonce = Nonce::from_slice(nonce);
         let payload = Payload {
             msg: data,
             aad: authenticated_data,
@@ -59,7 +73,7 @@ impl UmbralDEM {
         ciphertext: impl AsRef<[u8]>,
         authenticated_data: &[u8],
     ) -> Option<Box<[u8]>> {
-        let nonce_size = <<ChaCha20Poly1305 as AeadInPlace>::NonceSize as Unsigned>::to_usize();
+        let nonce_size = NonceSize::to_usize();
         let buf_size = ciphertext.as_ref().len();
 
         if buf_size < nonce_size {
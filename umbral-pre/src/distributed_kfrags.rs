@@ -0,0 +1,852 @@
+use crate::capsule::lambda_coeff;
+use crate::curve::{CurvePoint, CurveScalar, PublicKey, SecretKey};
+use crate::hashing::{HashingMode, ScalarDigest};
+use crate::hashing_ds::{hash_to_kfrag_id, hash_to_polynomial_arg, hash_to_shared_secret};
+use crate::key_frag::{
+    poly_eval, KeyFrag, KeyFragID, KeyFragProof, KeyFragProofSize, SignatureScheme, ValidityPeriod,
+};
+use crate::params::Parameters;
+use crate::threshold_decryption::{PublicKeyShare, SecretKeyShare};
+use crate::traits::SerializableToArray;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use typenum::{op, Unsigned};
+
+/// Errors that can happen when signing the fragments of a distributed generation
+/// with [`DistributedKeyFragSetup::sign()`].
+#[derive(Debug, PartialEq)]
+pub enum DistributedKeyFragError {
+    /// No public key share is given for the participant with the given index.
+    MissingPublicShare(u32),
+    /// The public key shares of the participants do not combine into the delegating key.
+    InconsistentPublicShares,
+    /// No contribution commitment is given for the participant with the given index.
+    MissingContribution(u32),
+    /// The participant with the given index has more than one contribution commitment.
+    RepeatingContribution(u32),
+    /// A contribution commitment is given for a member that is not a participant.
+    UnexpectedContribution(u32),
+    /// The contribution of the participant with the given index is not derived
+    /// from its key share, or does not match the threshold of the setup.
+    InvalidContribution(u32),
+}
+
+impl fmt::Display for DistributedKeyFragError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPublicShare(member) => {
+                write!(f, "No public key share for participant {}", member)
+            }
+            Self::InconsistentPublicShares => write!(
+                f,
+                "Public key shares do not combine into the delegating key"
+            ),
+            Self::MissingContribution(member) => {
+                write!(f, "No contribution from participant {}", member)
+            }
+            Self::RepeatingContribution(member) => {
+                write!(f, "Repeating contributions from participant {}", member)
+            }
+            Self::UnexpectedContribution(member) => {
+                write!(f, "Contribution from a non-participant {}", member)
+            }
+            Self::InvalidContribution(member) => {
+                write!(f, "Invalid contribution from participant {}", member)
+            }
+        }
+    }
+}
+
+type ParametersSize = <Parameters as SerializableToArray>::Size;
+type PublicKeySize = <PublicKey as SerializableToArray>::Size;
+type KeyFragIDSize = <KeyFragID as SerializableToArray>::Size;
+type PointSize = <CurvePoint as SerializableToArray>::Size;
+type ScalarSize = <CurveScalar as SerializableToArray>::Size;
+type U32Size = <u32 as SerializableToArray>::Size;
+type BoolSize = <bool as SerializableToArray>::Size;
+
+/// The public parameters of a distributed [`KeyFrag`] generation,
+/// in which a committee holding shares of the delegating key
+/// (see [`split_secret_key()`](`crate::split_secret_key()`))
+/// creates key fragments without reconstructing the key.
+///
+/// The protocol goes as follows:
+/// 1. The coordinator creates the setup with [`new()`](`Self::new()`)
+///    and sends it to the participating committee members.
+///    The setup is secret with respect to the proxies,
+///    since it contains the Diffie-Hellman point of the delegation,
+///    from which the secret `d` shared with the receiving party
+///    and the share indices of the fragments are derived.
+/// 2. Each participant calls [`contribute()`](`Self::contribute()`),
+///    sends every resulting [`PartialKeyFrag`] to the proxy the fragment is intended for,
+///    and its [`ContributionCommitment`] to the coordinator.
+/// 3. The coordinator calls [`sign()`](`Self::sign()`) with the collected commitments,
+///    the participants' public key shares, and the committee's signing key,
+///    and sends every resulting [`UnkeyedKeyFrag`] to its proxy.
+///    The commitments are checked against the public key shares,
+///    so a participant contributing anything but its own key share is identified,
+///    and the signed fragments are guaranteed to share the delegating key.
+/// 4. Each proxy calls [`UnkeyedKeyFrag::assemble()`] with the partial fragments it received.
+///
+/// The resulting fragments are indistinguishable from the ones created by
+/// [`generate_kfrags()`](`crate::generate_kfrags()`).
+/// They have an unbounded validity period,
+/// use [`HashingMode::Sha256`], and are signed with [`SignatureScheme::Ecdsa`].
+///
+/// Since its size depends on the number of participants, it is serialized with
+/// [`to_bytes()`](`Self::to_bytes()`) instead of [`SerializableToArray`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistributedKeyFragSetup {
+    params: Parameters,
+    delegating_pk: PublicKey,
+    receiving_pk: PublicKey,
+    precursor: CurvePoint,
+    dh_point: CurvePoint,
+    threshold: u32,
+    num_kfrags: u32,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+    participants: Box<[u32]>,
+}
+
+impl DistributedKeyFragSetup {
+    /// Creates a setup for `num_kfrags` fragments, `threshold` of which are necessary
+    /// for decryption, with the committee members holding the key shares
+    /// with the indices from `participants`
+    /// (at least as many as the threshold of the key shares).
+    ///
+    /// The rest of the parameters are the same as in
+    /// [`generate_kfrags()`](`crate::generate_kfrags()`).
+    ///
+    /// Returns `None` if `participants` is empty or contains repeating or zero indices,
+    /// or if `threshold` or `num_kfrags` is zero.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        params: &Parameters,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        participants: &[u32],
+        threshold: usize,
+        num_kfrags: usize,
+        sign_delegating_key: bool,
+        sign_receiving_key: bool,
+    ) -> Option<Self> {
+        if threshold == 0 || num_kfrags == 0 || !valid_participants(participants) {
+            return None;
+        }
+
+        let g = CurvePoint::generator();
+        let bob_pubkey_point = receiving_pk.to_point();
+
+        let (precursor, dh_point) = loop {
+            let private_precursor = CurveScalar::random_nonzero();
+            let precursor = &g * &private_precursor;
+            let dh_point = &bob_pubkey_point * &private_precursor;
+
+            // See the corresponding loop in `generate_kfrags()`.
+            let d = hash_to_shared_secret(&precursor, &bob_pubkey_point, &dh_point);
+            if !d.is_zero() {
+                break (precursor, dh_point);
+            }
+        };
+
+        Some(Self {
+            params: *params,
+            delegating_pk: *delegating_pk,
+            receiving_pk: *receiving_pk,
+            precursor,
+            dh_point,
+            threshold: threshold as u32,
+            num_kfrags: num_kfrags as u32,
+            sign_delegating_key,
+            sign_receiving_key,
+            participants: participants.into(),
+        })
+    }
+
+    fn shared_secret(&self) -> CurveScalar {
+        hash_to_shared_secret(
+            &self.precursor,
+            &self.receiving_pk.to_point(),
+            &self.dh_point,
+        )
+    }
+
+    fn kfrag_id(&self, index: u32) -> KeyFragID {
+        hash_to_kfrag_id(&self.precursor, &self.dh_point, index)
+    }
+
+    fn share_index(&self, kfrag_id: &KeyFragID) -> CurveScalar {
+        hash_to_polynomial_arg(
+            &self.precursor,
+            &self.receiving_pk.to_point(),
+            &self.dh_point,
+            kfrag_id,
+        )
+    }
+
+    /// Returns the factor the key share of the participant at `position`
+    /// is multiplied by to get the constant term of its polynomial.
+    fn contribution_factor(&self, position: usize) -> CurveScalar {
+        // The participants' Lagrange coefficients turn their key shares
+        // into additive shares of the delegating key.
+        // The participant indices are distinct and non-zero, so the coefficient always exists.
+        let lambda = lambda_coeff(&self.participant_xs(), position).unwrap();
+        // `invert()` is guaranteed not to panic because `d` is nonzero.
+        let inv_d = self.shared_secret().invert().unwrap();
+        &lambda * &inv_d
+    }
+
+    fn participant_xs(&self) -> Vec<CurveScalar> {
+        self.participants
+            .iter()
+            .map(|member| CurveScalar::from_u32(*member))
+            .collect()
+    }
+
+    /// Creates the contribution of the holder of `key_share` to every fragment,
+    /// and the commitment to it.
+    ///
+    /// Returns `None` if the key share is not one of the participants,
+    /// or there are fewer participants than the threshold of the key shares.
+    pub fn contribute(
+        &self,
+        key_share: &SecretKeyShare,
+    ) -> Option<(Box<[PartialKeyFrag]>, ContributionCommitment)> {
+        let position = self
+            .participants
+            .iter()
+            .position(|member| *member == key_share.index())?;
+        if self.participants.len() < key_share.threshold() {
+            return None;
+        }
+
+        let share_scalar = key_share.key.to_secret_scalar();
+        let factor = self.contribution_factor(position);
+
+        // The sum of the participants' polynomials is a generating polynomial
+        // with the constant term `delegating_sk / d`, as in `generate_kfrags()`.
+        let mut coefficients = Vec::<CurveScalar>::with_capacity(self.threshold as usize);
+        coefficients.push(&factor * &share_scalar);
+        for _i in 1..self.threshold {
+            coefficients.push(CurveScalar::random_nonzero());
+        }
+
+        let mut result = Vec::<PartialKeyFrag>::with_capacity(self.num_kfrags as usize);
+        for i in 0..self.num_kfrags {
+            let id = self.kfrag_id(i);
+            let key = poly_eval(&coefficients, &self.share_index(&id));
+            result.push(PartialKeyFrag {
+                id,
+                member: key_share.index(),
+                key,
+            });
+        }
+
+        let commitment =
+            ContributionCommitment::new(&self.params, key_share, &factor, &coefficients);
+
+        Some((result.into_boxed_slice(), commitment))
+    }
+
+    /// Signs the fragments with `signing_sk`, given the commitments
+    /// to the contributions of all the participants, and their public key shares
+    /// (see [`SecretKeyShare::public_share()`](`crate::SecretKeyShare::public_share()`)).
+    ///
+    /// Fails if the public key shares do not combine into the delegating key,
+    /// or if some of the commitments are missing, repeated, or not derived from
+    /// the respective key share (identifying the participant at fault).
+    pub fn sign(
+        &self,
+        signing_sk: &SecretKey,
+        commitments: &[ContributionCommitment],
+        public_shares: &[PublicKeyShare],
+    ) -> Result<Box<[UnkeyedKeyFrag]>, DistributedKeyFragError> {
+        let xs = self.participant_xs();
+
+        // Make sure the shares used to check the contributions are the shares of the delegating key.
+        let mut combined_pk = CurvePoint::identity();
+        for (position, member) in self.participants.iter().enumerate() {
+            let public_share = public_shares
+                .iter()
+                .find(|share| share.index == *member)
+                .ok_or(DistributedKeyFragError::MissingPublicShare(*member))?;
+            // The participant indices are distinct and non-zero, so the coefficient always exists.
+            let lambda = lambda_coeff(&xs, position).unwrap();
+            combined_pk = &combined_pk + &(&public_share.key.to_point() * &lambda);
+        }
+        if combined_pk != self.delegating_pk.to_point() {
+            return Err(DistributedKeyFragError::InconsistentPublicShares);
+        }
+
+        if let Some(unexpected) = commitments
+            .iter()
+            .find(|commitment| !self.participants.contains(&commitment.member))
+        {
+            return Err(DistributedKeyFragError::UnexpectedContribution(
+                unexpected.member,
+            ));
+        }
+
+        let mut member_commitments = Vec::<&ContributionCommitment>::new();
+        for (position, member) in self.participants.iter().enumerate() {
+            let mut matching = commitments.iter().filter(|c| c.member == *member);
+            let commitment = matching
+                .next()
+                .ok_or(DistributedKeyFragError::MissingContribution(*member))?;
+            if matching.next().is_some() {
+                return Err(DistributedKeyFragError::RepeatingContribution(*member));
+            }
+
+            // Checked to be present above
+            let public_share = public_shares
+                .iter()
+                .find(|share| share.index == *member)
+                .unwrap();
+            let factor = self.contribution_factor(position);
+            if commitment.coefficients.len() != self.threshold as usize
+                || !commitment.verify(&self.params, public_share, &factor)
+            {
+                return Err(DistributedKeyFragError::InvalidContribution(*member));
+            }
+
+            member_commitments.push(commitment);
+        }
+
+        let mut result = Vec::<UnkeyedKeyFrag>::with_capacity(self.num_kfrags as usize);
+        for i in 0..self.num_kfrags {
+            let id = self.kfrag_id(i);
+            let share_index = self.share_index(&id);
+
+            let commitment = member_commitments
+                .iter()
+                .fold(CurvePoint::identity(), |sum, member_commitment| {
+                    &sum + &member_commitment.evaluate(&share_index)
+                });
+
+            let proof = KeyFragProof::new(
+                &id,
+                &commitment,
+                &self.precursor,
+                signing_sk,
+                &self.delegating_pk,
+                &self.receiving_pk,
+                self.sign_delegating_key,
+                self.sign_receiving_key,
+                self.threshold,
+                self.num_kfrags,
+                &ValidityPeriod::unbounded(),
+                HashingMode::Sha256,
+                SignatureScheme::Ecdsa,
+                false,
+            );
+
+            result.push(UnkeyedKeyFrag {
+                params: self.params,
+                id,
+                precursor: self.precursor,
+                proof,
+            });
+        }
+        Ok(result.into_boxed_slice())
+    }
+
+    /// Produces a byte array with the setup's contents.
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        let mut result = Vec::<u8>::new();
+        result.extend_from_slice(&self.params.to_array());
+        result.extend_from_slice(&self.delegating_pk.to_array());
+        result.extend_from_slice(&self.receiving_pk.to_array());
+        result.extend_from_slice(&self.precursor.to_array());
+        result.extend_from_slice(&self.dh_point.to_array());
+        result.extend_from_slice(&self.threshold.to_array());
+        result.extend_from_slice(&self.num_kfrags.to_array());
+        result.extend_from_slice(&self.sign_delegating_key.to_array());
+        result.extend_from_slice(&self.sign_receiving_key.to_array());
+        for member in self.participants.iter() {
+            result.extend_from_slice(&member.to_array());
+        }
+        result.into_boxed_slice()
+    }
+
+    /// Attempts to produce the setup back from the serialized form.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Option<Self> {
+        type HeaderSize = op!(ParametersSize
+            + PublicKeySize
+            + PublicKeySize
+            + PointSize
+            + PointSize
+            + U32Size
+            + U32Size
+            + BoolSize
+            + BoolSize);
+
+        let bytes = bytes.as_ref();
+        let header_size = HeaderSize::to_usize();
+        let u32_size = U32Size::to_usize();
+
+        if bytes.len() <= header_size || (bytes.len() - header_size) % u32_size != 0 {
+            return None;
+        }
+
+        let (header, rest) = bytes.split_at(header_size);
+        let header = GenericArray::<u8, HeaderSize>::from_slice(header);
+        let (params, header) = Parameters::take(*header)?;
+        let (delegating_pk, header) = PublicKey::take(header)?;
+        let (receiving_pk, header) = PublicKey::take(header)?;
+        let (precursor, header) = CurvePoint::take(header)?;
+        let (dh_point, header) = CurvePoint::take(header)?;
+        let (threshold, header) = u32::take(header)?;
+        let (num_kfrags, header) = u32::take(header)?;
+        let (sign_delegating_key, header) = bool::take(header)?;
+        let sign_receiving_key = bool::take_last(header)?;
+        let participants = rest
+            .chunks(u32_size)
+            .map(u32::from_bytes)
+            .collect::<Option<Vec<_>>>()?;
+
+        let setup = Self {
+            params,
+            delegating_pk,
+            receiving_pk,
+            precursor,
+            dh_point,
+            threshold,
+            num_kfrags,
+            sign_delegating_key,
+            sign_receiving_key,
+            participants: participants.into_boxed_slice(),
+        };
+
+        if threshold == 0
+            || num_kfrags == 0
+            || !valid_participants(&setup.participants)
+            || setup.shared_secret().is_zero()
+        {
+            return None;
+        }
+
+        Some(setup)
+    }
+}
+
+fn valid_participants(participants: &[u32]) -> bool {
+    !participants.is_empty()
+        && participants
+            .iter()
+            .enumerate()
+            .all(|(i, member)| *member != 0 && !participants[..i].contains(member))
+}
+
+/// A committee member's contribution to a single [`KeyFrag`].
+///
+/// It must be sent privately to the proxy the fragment is intended for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartialKeyFrag {
+    id: KeyFragID,
+    member: u32,
+    key: CurveScalar,
+}
+
+impl SerializableToArray for PartialKeyFrag {
+    type Size = op!(KeyFragIDSize + U32Size + ScalarSize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.id
+            .to_array()
+            .concat(self.member.to_array())
+            .concat(self.key.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (id, rest) = KeyFragID::take(*arr)?;
+        let (member, rest) = u32::take(rest)?;
+        let key = CurveScalar::take_last(rest)?;
+        Some(Self { id, member, key })
+    }
+}
+
+impl PartialKeyFrag {
+    /// Returns the identifier of the fragment this contribution is for.
+    pub fn id(&self) -> KeyFragID {
+        self.id
+    }
+}
+
+/// A public commitment to the contributions of a committee member,
+/// to be sent to the coordinator.
+///
+/// It consists of a Feldman commitment to the member's polynomial,
+/// and a proof that the constant term of the polynomial is derived from the member's key share,
+/// checked against its public key share in [`DistributedKeyFragSetup::sign()`].
+///
+/// Since its size depends on the threshold, it is serialized with
+/// [`to_bytes()`](`Self::to_bytes()`) instead of [`SerializableToArray`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContributionCommitment {
+    member: u32,
+    // `u` multiplied by each of the coefficients, starting from the constant one.
+    coefficients: Box<[CurvePoint]>,
+    point_g2: CurvePoint,
+    point_w2: CurvePoint,
+    signature: CurveScalar,
+}
+
+fn contribution_challenge(
+    share_pk: &CurvePoint,
+    w: &CurvePoint,
+    w1: &CurvePoint,
+    g2: &CurvePoint,
+    w2: &CurvePoint,
+) -> CurveScalar {
+    ScalarDigest::new_with_dst(b"KFRAG_CONTRIBUTION")
+        .chain_points(&[CurvePoint::generator(), *share_pk, *w, *w1, *g2, *w2])
+        .finalize()
+}
+
+impl ContributionCommitment {
+    /// Proves that the constant term of the polynomial is the key share multiplied by `factor`,
+    /// that is, the discrete logarithms of the public key share (base `g`)
+    /// and of the committed constant term (base `w = u * factor`) are equal.
+    fn new(
+        params: &Parameters,
+        key_share: &SecretKeyShare,
+        factor: &CurveScalar,
+        coefficients: &[CurveScalar],
+    ) -> Self {
+        let g = CurvePoint::generator();
+        let u = params.u;
+        let w = &u * factor;
+        let x = key_share.key.to_secret_scalar();
+
+        let coefficients: Vec<CurvePoint> = coefficients.iter().map(|coeff| &u * coeff).collect();
+
+        let t = CurveScalar::random_nonzero();
+        let g2 = &g * &t;
+        let w2 = &w * &t;
+        let h = contribution_challenge(&(&g * &x), &w, &coefficients[0], &g2, &w2);
+        let z = &t + &(&x * &h);
+
+        Self {
+            member: key_share.index(),
+            coefficients: coefficients.into_boxed_slice(),
+            point_g2: g2,
+            point_w2: w2,
+            signature: z,
+        }
+    }
+
+    fn verify(
+        &self,
+        params: &Parameters,
+        public_share: &PublicKeyShare,
+        factor: &CurveScalar,
+    ) -> bool {
+        let g = CurvePoint::generator();
+        let w = &params.u * factor;
+        let share_pk = public_share.key.to_point();
+        let w1 = self.coefficients[0];
+        let z = self.signature;
+
+        let h = contribution_challenge(&share_pk, &w, &w1, &self.point_g2, &self.point_w2);
+
+        &g * &z == &self.point_g2 + &(&share_pk * &h) && &w * &z == &self.point_w2 + &(&w1 * &h)
+    }
+
+    /// Returns `u` multiplied by the value of the member's polynomial at `x`.
+    fn evaluate(&self, x: &CurveScalar) -> CurvePoint {
+        let mut result = self.coefficients[self.coefficients.len() - 1];
+        for coeff in self.coefficients[..self.coefficients.len() - 1]
+            .iter()
+            .rev()
+        {
+            result = &(&result * x) + coeff;
+        }
+        result
+    }
+
+    /// Produces a byte array with the commitment's contents.
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        let mut result = Vec::<u8>::new();
+        result.extend_from_slice(&self.member.to_array());
+        result.extend_from_slice(&self.point_g2.to_array());
+        result.extend_from_slice(&self.point_w2.to_array());
+        result.extend_from_slice(&self.signature.to_array());
+        for coeff in self.coefficients.iter() {
+            result.extend_from_slice(&coeff.to_array());
+        }
+        result.into_boxed_slice()
+    }
+
+    /// Attempts to produce the commitment back from the serialized form.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Option<Self> {
+        type HeaderSize = op!(U32Size + PointSize + PointSize + ScalarSize);
+
+        let bytes = bytes.as_ref();
+        let header_size = HeaderSize::to_usize();
+        let point_size = PointSize::to_usize();
+
+        if bytes.len() <= header_size || (bytes.len() - header_size) % point_size != 0 {
+            return None;
+        }
+
+        let (header, rest) = bytes.split_at(header_size);
+        let header = GenericArray::<u8, HeaderSize>::from_slice(header);
+        let (member, header) = u32::take(*header)?;
+        let (point_g2, header) = CurvePoint::take(header)?;
+        let (point_w2, header) = CurvePoint::take(header)?;
+        let signature = CurveScalar::take_last(header)?;
+        let coefficients = rest
+            .chunks(point_size)
+            .map(CurvePoint::from_bytes)
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            member,
+            coefficients: coefficients.into_boxed_slice(),
+            point_g2,
+            point_w2,
+            signature,
+        })
+    }
+}
+
+/// A signed [`KeyFrag`] without the re-encryption key share,
+/// which is assembled by the proxy from the committee members' contributions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnkeyedKeyFrag {
+    params: Parameters,
+    id: KeyFragID,
+    precursor: CurvePoint,
+    proof: KeyFragProof,
+}
+
+impl SerializableToArray for UnkeyedKeyFrag {
+    type Size = op!(ParametersSize + KeyFragIDSize + PointSize + KeyFragProofSize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.params
+            .to_array()
+            .concat(self.id.to_array())
+            .concat(self.precursor.to_array())
+            .concat(self.proof.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (params, rest) = Parameters::take(*arr)?;
+        let (id, rest) = KeyFragID::take(rest)?;
+        let (precursor, rest) = CurvePoint::take(rest)?;
+        let proof = KeyFragProof::take_last(rest)?;
+        Some(Self {
+            params,
+            id,
+            precursor,
+            proof,
+        })
+    }
+}
+
+impl UnkeyedKeyFrag {
+    /// Returns the identifier of the fragment.
+    pub fn id(&self) -> KeyFragID {
+        self.id
+    }
+
+    /// Combines the contributions of all the participants into the key fragment.
+    ///
+    /// Returns `None` if some of the contributions are for a different fragment,
+    /// repeated, missing, or inconsistent with the signed commitment
+    /// (which is checked by the coordinator to share the delegating key,
+    /// so the participants cannot make the proxy accept a different one).
+    /// One should still call [`KeyFrag::verify()`] on the result to check the signature.
+    pub fn assemble(&self, partial_kfrags: &[PartialKeyFrag]) -> Option<KeyFrag> {
+        let mut key = CurveScalar::default();
+        for (i, partial) in partial_kfrags.iter().enumerate() {
+            if partial.id != self.id
+                || partial_kfrags[..i]
+                    .iter()
+                    .any(|other| other.member == partial.member)
+            {
+                return None;
+            }
+            key = &key + &partial.key;
+        }
+
+        if &self.params.u * &key != self.proof.commitment {
+            return None;
+        }
+
+        Some(KeyFrag {
+            params: self.params,
+            id: self.id,
+            key,
+            precursor: self.precursor,
+            proof: self.proof.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{
+        ContributionCommitment, DistributedKeyFragError, DistributedKeyFragSetup, PartialKeyFrag,
+    };
+    use crate::{
+        decrypt_reencrypted, encrypt, reencrypt, split_secret_key, verify_kfrags, KeyFrag,
+        Parameters, PublicKey, SecretKey, SerializableToArray,
+    };
+
+    #[test]
+    fn test_distributed_kfrags() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let key_shares = split_secret_key(&delegating_sk, 2, 3).unwrap();
+        let participants = [1, 3];
+
+        let setup = DistributedKeyFragSetup::new(
+            &params,
+            &delegating_pk,
+            &receiving_pk,
+            &participants,
+            2,
+            3,
+            true,
+            true,
+        )
+        .unwrap();
+        let setup_back = DistributedKeyFragSetup::from_bytes(setup.to_bytes()).unwrap();
+        assert_eq!(setup, setup_back);
+
+        // The second key share is not participating.
+        assert!(setup.contribute(&key_shares[1]).is_none());
+
+        let contributions: Vec<_> = [&key_shares[0], &key_shares[2]]
+            .iter()
+            .map(|key_share| setup.contribute(key_share).unwrap())
+            .collect();
+
+        let commitments: Vec<_> = contributions
+            .iter()
+            .map(|(_partials, commitment)| commitment.clone())
+            .collect();
+        for commitment in commitments.iter() {
+            let commitment_back =
+                ContributionCommitment::from_bytes(commitment.to_bytes()).unwrap();
+            assert_eq!(commitment, &commitment_back);
+        }
+
+        let public_shares: Vec<_> = key_shares
+            .iter()
+            .map(|share| share.public_share())
+            .collect();
+
+        assert_eq!(
+            setup.sign(&signing_sk, &commitments[1..], &public_shares),
+            Err(DistributedKeyFragError::MissingContribution(1))
+        );
+        assert_eq!(
+            setup.sign(
+                &signing_sk,
+                &[commitments[0].clone(), commitments[0].clone()],
+                &public_shares
+            ),
+            Err(DistributedKeyFragError::RepeatingContribution(1))
+        );
+        assert_eq!(
+            setup.sign(&signing_sk, &commitments, &public_shares[..2]),
+            Err(DistributedKeyFragError::MissingPublicShare(3))
+        );
+
+        // Public shares of a different key cannot be used to check the contributions.
+        let other_shares = split_secret_key(&SecretKey::random(), 2, 3).unwrap();
+        let other_public_shares: Vec<_> = other_shares
+            .iter()
+            .map(|share| share.public_share())
+            .collect();
+        assert_eq!(
+            setup.sign(&signing_sk, &commitments, &other_public_shares),
+            Err(DistributedKeyFragError::InconsistentPublicShares)
+        );
+
+        // A participant contributing something other than its key share is identified.
+        let (_partials, bad_commitment) = setup.contribute(&other_shares[2]).unwrap();
+        assert_eq!(
+            setup.sign(
+                &signing_sk,
+                &[commitments[0].clone(), bad_commitment],
+                &public_shares
+            ),
+            Err(DistributedKeyFragError::InvalidContribution(3))
+        );
+
+        let unkeyed_kfrags = setup
+            .sign(&signing_sk, &commitments, &public_shares)
+            .unwrap();
+
+        let kfrags: Vec<KeyFrag> = unkeyed_kfrags
+            .iter()
+            .map(|unkeyed| {
+                let partials: Vec<PartialKeyFrag> = contributions
+                    .iter()
+                    .flat_map(|(partials, _commitment)| {
+                        partials.iter().filter(|p| p.id() == unkeyed.id())
+                    })
+                    .cloned()
+                    .collect();
+
+                // All the contributions are necessary.
+                assert!(unkeyed.assemble(&partials[..1]).is_none());
+
+                unkeyed.assemble(&partials).unwrap()
+            })
+            .collect();
+
+        for kfrag in kfrags.iter() {
+            assert!(kfrag.verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk)));
+            let kfrag_back = KeyFrag::from_array(&kfrag.to_array()).unwrap();
+            assert_eq!(kfrag, &kfrag_back);
+        }
+        assert!(verify_kfrags(
+            &kfrags,
+            &signing_pk,
+            Some(&delegating_pk),
+            Some(&receiving_pk)
+        ));
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let cfrags: Vec<_> = kfrags[1..]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+        for cfrag in cfrags.iter() {
+            assert!(cfrag.verify(&capsule, &delegating_pk, &receiving_pk, &signing_pk));
+        }
+
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+}
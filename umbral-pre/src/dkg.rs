@@ -0,0 +1,723 @@
+//! Joint-Feldman distributed key generation,
+//! producing shares of a secret key that is never held by a single party.
+//!
+//! Note that the joint-Feldman protocol does not guarantee a uniformly random key:
+//! a rushing participant that sees the others' commitments before broadcasting its own
+//! can make the others disqualify it (by not answering a complaint) or not,
+//! biasing the resulting public key.
+//! This does not help it learn the secret key,
+//! but the key must not be relied on to be unbiased.
+
+use crate::curve::{CurvePoint, CurveScalar, PublicKey, SecretKey};
+use crate::key_frag::poly_eval;
+use crate::threshold_decryption::{PublicKeyShare, SecretKeyShare};
+use crate::traits::SerializableToArray;
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use typenum::{op, Unsigned};
+
+/// Errors that can happen when finalizing the distributed key generation.
+#[derive(Debug, PartialEq)]
+pub enum DkgError {
+    /// A qualified participant's share was neither received directly
+    /// nor revealed in an answer to a complaint.
+    MissingShare {
+        /// The index of the participant whose share is missing.
+        sender: u32,
+    },
+    /// Fewer participants than the threshold remain after the disqualification.
+    NotEnoughQualified {
+        /// The threshold of the shared key.
+        threshold: usize,
+        /// The number of qualified participants.
+        qualified: usize,
+    },
+    /// This participant was disqualified by the others.
+    Disqualified,
+}
+
+impl fmt::Display for DkgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingShare { sender } => {
+                write!(f, "Missing the share from participant {}", sender)
+            }
+            Self::NotEnoughQualified {
+                threshold,
+                qualified,
+            } => write!(
+                f,
+                "Not enough qualified participants: {} (threshold is {})",
+                qualified, threshold
+            ),
+            Self::Disqualified => write!(f, "The participant was disqualified"),
+        }
+    }
+}
+
+type PointSize = <CurvePoint as SerializableToArray>::Size;
+type ScalarSize = <CurveScalar as SerializableToArray>::Size;
+type U32Size = <u32 as SerializableToArray>::Size;
+
+/// Evaluates a polynomial "in the exponent" at `x`.
+fn evaluate_commitments(points: &[CurvePoint], x: &CurveScalar) -> CurvePoint {
+    let mut result = points[points.len() - 1];
+    for point in points[..points.len() - 1].iter().rev() {
+        result = &(&result * x) + point;
+    }
+    result
+}
+
+/// A broadcast message with the Feldman commitments to a participant's polynomial.
+///
+/// Since its size depends on the threshold, it is serialized with
+/// [`to_bytes()`](`Self::to_bytes()`) instead of [`SerializableToArray`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkgCommitment {
+    sender: u32,
+    // The generator multiplied by each of the coefficients, starting from the constant one.
+    points: Box<[CurvePoint]>,
+}
+
+impl DkgCommitment {
+    /// Returns the index of the participant that created the commitment.
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    fn verify_share(&self, share: &DkgShare) -> bool {
+        share.sender == self.sender
+            && &CurvePoint::generator() * &share.value
+                == evaluate_commitments(&self.points, &CurveScalar::from_u32(share.receiver))
+    }
+
+    /// Produces a byte array with the commitment's contents.
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        let mut result = Vec::<u8>::new();
+        result.extend_from_slice(&self.sender.to_array());
+        for point in self.points.iter() {
+            result.extend_from_slice(&point.to_array());
+        }
+        result.into_boxed_slice()
+    }
+
+    /// Attempts to produce the commitment back from the serialized form.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Option<Self> {
+        let bytes = bytes.as_ref();
+        let point_size = PointSize::to_usize();
+        let header_size = U32Size::to_usize();
+
+        if bytes.len() <= header_size || (bytes.len() - header_size) % point_size != 0 {
+            return None;
+        }
+
+        let (sender_bytes, rest) = bytes.split_at(header_size);
+        let sender = u32::from_bytes(sender_bytes)?;
+        let points = rest
+            .chunks(point_size)
+            .map(CurvePoint::from_bytes)
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            sender,
+            points: points.into_boxed_slice(),
+        })
+    }
+}
+
+/// A participant's share for another participant.
+///
+/// It must be sent privately to the receiver, unless it is revealed
+/// in an answer to a [`DkgComplaint`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkgShare {
+    sender: u32,
+    receiver: u32,
+    value: CurveScalar,
+}
+
+impl SerializableToArray for DkgShare {
+    type Size = op!(U32Size + U32Size + ScalarSize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.sender
+            .to_array()
+            .concat(self.receiver.to_array())
+            .concat(self.value.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (sender, rest) = u32::take(*arr)?;
+        let (receiver, rest) = u32::take(rest)?;
+        let value = CurveScalar::take_last(rest)?;
+        Some(Self {
+            sender,
+            receiver,
+            value,
+        })
+    }
+}
+
+impl DkgShare {
+    /// Returns the index of the participant that created the share.
+    pub fn sender(&self) -> u32 {
+        self.sender
+    }
+
+    /// Returns the index of the participant the share is intended for.
+    pub fn receiver(&self) -> u32 {
+        self.receiver
+    }
+}
+
+/// A broadcast message stating that the share from the accused participant
+/// was either not received or inconsistent with its commitment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DkgComplaint {
+    complainer: u32,
+    accused: u32,
+}
+
+impl SerializableToArray for DkgComplaint {
+    type Size = op!(U32Size + U32Size);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.complainer.to_array().concat(self.accused.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (complainer, rest) = u32::take(*arr)?;
+        let accused = u32::take_last(rest)?;
+        Some(Self {
+            complainer,
+            accused,
+        })
+    }
+}
+
+impl DkgComplaint {
+    /// Returns the index of the participant that made the complaint.
+    pub fn complainer(&self) -> u32 {
+        self.complainer
+    }
+
+    /// Returns the index of the participant the complaint is made against.
+    pub fn accused(&self) -> u32 {
+        self.accused
+    }
+}
+
+/// The result of the distributed key generation for a single participant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DkgOutput {
+    secret_share: SecretKeyShare,
+    // The sum of the qualified participants' commitments.
+    commitments: Box<[CurvePoint]>,
+    disqualified: Box<[u32]>,
+}
+
+impl DkgOutput {
+    /// Returns the participant's share of the joint secret key.
+    pub fn secret_share(&self) -> &SecretKeyShare {
+        &self.secret_share
+    }
+
+    /// Returns the joint public key, to be used in [`encrypt()`](`crate::encrypt()`).
+    ///
+    /// Returns `None` if the joint commitment to the constant term is the identity,
+    /// which only happens if the qualified participants' commitments cancel out.
+    pub fn public_key(&self) -> Option<PublicKey> {
+        PublicKey::from_point(&self.commitments[0])
+    }
+
+    /// Returns the public counterpart of the share of the participant with the given index,
+    /// which can be used to verify its [`DecryptionShare`](`crate::DecryptionShare`) objects.
+    pub fn public_share(&self, index: u32) -> Option<PublicKeyShare> {
+        if index == 0 {
+            return None;
+        }
+        let point = evaluate_commitments(&self.commitments, &CurveScalar::from_u32(index));
+        Some(PublicKeyShare::new(
+            index,
+            self.secret_share.threshold() as u32,
+            PublicKey::from_point(&point)?,
+        ))
+    }
+
+    /// Returns the indices of the participants excluded from the joint key.
+    pub fn disqualified(&self) -> &[u32] {
+        &self.disqualified
+    }
+}
+
+/// A participant of the distributed key generation.
+///
+/// The protocol goes as follows:
+/// 1. Each participant broadcasts its [`commitment()`](`Self::commitment()`),
+///    and sends the result of [`share_for()`](`Self::share_for()`)
+///    privately to each of the other participants.
+/// 2. Each participant passes the received messages to
+///    [`receive_commitment()`](`Self::receive_commitment()`) and
+///    [`receive_share()`](`Self::receive_share()`), and broadcasts
+///    the [`complaints()`](`Self::complaints()`) about the missing or invalid shares.
+/// 3. The accused participants broadcast their
+///    [`answer_complaint()`](`Self::answer_complaint()`) results.
+///    Each participant passes all the complaints and answers to
+///    [`receive_complaint()`](`Self::receive_complaint()`) and
+///    [`receive_answer()`](`Self::receive_answer()`).
+/// 4. Each participant calls [`finalize()`](`Self::finalize()`).
+///    The participants with unresolved complaints against them are disqualified,
+///    and the joint key is made from the rest.
+pub struct DkgParticipant {
+    index: u32,
+    num_participants: u32,
+    coefficients: Box<[CurveScalar]>,
+    commitments: Vec<DkgCommitment>,
+    shares: Vec<DkgShare>,
+    complaints: Vec<DkgComplaint>,
+    answers: Vec<DkgShare>,
+}
+
+impl DkgParticipant {
+    /// Creates the participant with the given index (starting from 1)
+    /// in a group of `num_participants`, any `threshold` of which will be able
+    /// to use the resulting key.
+    ///
+    /// Returns `None` if the index is out of range, or `threshold` is zero
+    /// or greater than `num_participants`.
+    pub fn new(index: u32, threshold: usize, num_participants: usize) -> Option<Self> {
+        if index == 0 || index as usize > num_participants {
+            return None;
+        }
+        if threshold == 0 || threshold > num_participants {
+            return None;
+        }
+
+        let mut coefficients = Vec::<CurveScalar>::with_capacity(threshold);
+        for _i in 0..threshold {
+            coefficients.push(CurveScalar::random_nonzero());
+        }
+
+        let mut participant = Self {
+            index,
+            num_participants: num_participants as u32,
+            coefficients: coefficients.into_boxed_slice(),
+            commitments: Vec::new(),
+            shares: Vec::new(),
+            complaints: Vec::new(),
+            answers: Vec::new(),
+        };
+        participant.commitments.push(participant.commitment());
+        Some(participant)
+    }
+
+    /// Returns the index of the participant.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the commitment to the participant's polynomial, to be broadcast.
+    pub fn commitment(&self) -> DkgCommitment {
+        let g = CurvePoint::generator();
+        let points: Vec<CurvePoint> = self.coefficients.iter().map(|coeff| &g * coeff).collect();
+        DkgCommitment {
+            sender: self.index,
+            points: points.into_boxed_slice(),
+        }
+    }
+
+    /// Returns the share for the participant with the given index, to be sent privately.
+    pub fn share_for(&self, receiver: u32) -> Option<DkgShare> {
+        if receiver == 0 || receiver > self.num_participants {
+            return None;
+        }
+        Some(DkgShare {
+            sender: self.index,
+            receiver,
+            value: poly_eval(&self.coefficients, &CurveScalar::from_u32(receiver)),
+        })
+    }
+
+    fn commitment_from(&self, sender: u32) -> Option<&DkgCommitment> {
+        self.commitments
+            .iter()
+            .find(|commitment| commitment.sender == sender)
+    }
+
+    /// Checks the share against the commitment of its sender, if one was received.
+    fn has_valid_share(&self, share: &DkgShare) -> bool {
+        match self.commitment_from(share.sender) {
+            Some(commitment) => commitment.verify_share(share),
+            None => false,
+        }
+    }
+
+    /// Records a commitment broadcast by another participant.
+    ///
+    /// Returns `false` if the commitment is malformed
+    /// or a commitment from the same participant was already received.
+    pub fn receive_commitment(&mut self, commitment: &DkgCommitment) -> bool {
+        if commitment.sender == 0
+            || commitment.sender > self.num_participants
+            || commitment.points.len() != self.coefficients.len()
+            || self.commitment_from(commitment.sender).is_some()
+        {
+            return false;
+        }
+        self.commitments.push(commitment.clone());
+        true
+    }
+
+    /// Records a share sent to this participant.
+    ///
+    /// The commitment of the sender must be received first.
+    /// Returns `false` if the share is not intended for this participant,
+    /// is inconsistent with the sender's commitment, or was already received.
+    pub fn receive_share(&mut self, share: &DkgShare) -> bool {
+        let valid = share.receiver == self.index
+            && share.sender != self.index
+            && !self.shares.iter().any(|other| other.sender == share.sender)
+            && self.has_valid_share(share);
+        if valid {
+            self.shares.push(share.clone());
+        }
+        valid
+    }
+
+    /// Returns the complaints against the participants whose commitments were received,
+    /// but whose shares are either missing or were rejected, to be broadcast.
+    pub fn complaints(&self) -> Box<[DkgComplaint]> {
+        let complaints: Vec<DkgComplaint> = self
+            .commitments
+            .iter()
+            .filter(|commitment| {
+                commitment.sender != self.index
+                    && !self
+                        .shares
+                        .iter()
+                        .any(|share| share.sender == commitment.sender)
+            })
+            .map(|commitment| DkgComplaint {
+                complainer: self.index,
+                accused: commitment.sender,
+            })
+            .collect();
+        complaints.into_boxed_slice()
+    }
+
+    /// Returns the share for the complainer if the complaint is made against this participant,
+    /// to be broadcast.
+    pub fn answer_complaint(&self, complaint: &DkgComplaint) -> Option<DkgShare> {
+        if complaint.accused != self.index {
+            return None;
+        }
+        self.share_for(complaint.complainer)
+    }
+
+    /// Records a complaint broadcast by a participant (including this one).
+    ///
+    /// The commitment of the complainer must be received first.
+    /// Returns `false` if the complaint cannot be answered
+    /// (the indices are out of range, or the participant accuses itself),
+    /// or if the complainer's commitment was not received.
+    pub fn receive_complaint(&mut self, complaint: &DkgComplaint) -> bool {
+        if complaint.complainer == 0
+            || complaint.complainer > self.num_participants
+            || complaint.accused == 0
+            || complaint.accused > self.num_participants
+            || complaint.complainer == complaint.accused
+            || self.commitment_from(complaint.complainer).is_none()
+        {
+            return false;
+        }
+        if !self.complaints.contains(complaint) {
+            self.complaints.push(*complaint);
+        }
+        true
+    }
+
+    /// Records an answer to a complaint broadcast by an accused participant.
+    ///
+    /// Returns `false` if the answer is inconsistent with the accused participant's commitment.
+    pub fn receive_answer(&mut self, answer: &DkgShare) -> bool {
+        let valid = self.has_valid_share(answer);
+        if valid && !self.answers.contains(answer) {
+            self.answers.push(answer.clone());
+        }
+        valid
+    }
+
+    fn is_answered(&self, complaint: &DkgComplaint) -> bool {
+        self.answers.iter().any(|answer| {
+            answer.sender == complaint.accused && answer.receiver == complaint.complainer
+        })
+    }
+
+    /// Finishes the key generation, combining the shares of the qualified participants.
+    pub fn finalize(&self) -> Result<DkgOutput, DkgError> {
+        let mut qualified = Vec::<&DkgCommitment>::new();
+        let mut disqualified = Vec::<u32>::new();
+        for sender in 1..=self.num_participants {
+            let commitment = match self.commitment_from(sender) {
+                Some(commitment) => commitment,
+                None => {
+                    disqualified.push(sender);
+                    continue;
+                }
+            };
+            if self
+                .complaints
+                .iter()
+                .any(|complaint| complaint.accused == sender && !self.is_answered(complaint))
+            {
+                disqualified.push(sender);
+            } else {
+                qualified.push(commitment);
+            }
+        }
+
+        if disqualified.contains(&self.index) {
+            return Err(DkgError::Disqualified);
+        }
+
+        let threshold = self.coefficients.len();
+        if qualified.len() < threshold {
+            return Err(DkgError::NotEnoughQualified {
+                threshold,
+                qualified: qualified.len(),
+            });
+        }
+
+        let mut key = CurveScalar::default();
+        let mut commitments = vec![CurvePoint::identity(); threshold];
+        for commitment in qualified {
+            let sender = commitment.sender;
+            let value = if sender == self.index {
+                poly_eval(&self.coefficients, &CurveScalar::from_u32(self.index))
+            } else {
+                self.shares
+                    .iter()
+                    .chain(self.answers.iter())
+                    .find(|share| share.sender == sender && share.receiver == self.index)
+                    .ok_or(DkgError::MissingShare { sender })?
+                    .value
+            };
+            key = &key + &value;
+            for (joint, point) in commitments.iter_mut().zip(commitment.points.iter()) {
+                *joint = &*joint + point;
+            }
+        }
+
+        // The share is zero with a negligible probability.
+        let secret_share =
+            SecretKeyShare::new(self.index, threshold as u32, SecretKey::from_scalar(&key));
+
+        Ok(DkgOutput {
+            secret_share,
+            commitments: commitments.into_boxed_slice(),
+            disqualified: disqualified.into_boxed_slice(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{DkgCommitment, DkgComplaint, DkgError, DkgParticipant, DkgShare};
+    use crate::{
+        decrypt_original_threshold, encrypt, DecryptionShare, Parameters, SerializableToArray,
+    };
+
+    fn exchange_commitments(participants: &mut [DkgParticipant]) {
+        let commitments: Vec<DkgCommitment> = participants
+            .iter()
+            .map(|participant| {
+                DkgCommitment::from_bytes(participant.commitment().to_bytes()).unwrap()
+            })
+            .collect();
+        for participant in participants.iter_mut() {
+            for commitment in commitments.iter() {
+                if commitment.sender() != participant.index() {
+                    assert!(participant.receive_commitment(commitment));
+                }
+            }
+        }
+    }
+
+    fn exchange_complaints(participants: &mut [DkgParticipant]) -> Vec<DkgComplaint> {
+        let complaints: Vec<DkgComplaint> = participants
+            .iter()
+            .flat_map(|participant| participant.complaints().into_vec())
+            .collect();
+        let answers: Vec<DkgShare> = complaints
+            .iter()
+            .flat_map(|complaint| {
+                participants
+                    .iter()
+                    .filter_map(move |participant| participant.answer_complaint(complaint))
+            })
+            .collect();
+        for participant in participants.iter_mut() {
+            for complaint in complaints.iter() {
+                assert!(participant.receive_complaint(complaint));
+            }
+            for answer in answers.iter() {
+                assert!(participant.receive_answer(answer));
+            }
+        }
+        complaints
+    }
+
+    #[test]
+    fn test_dkg() {
+        let mut participants: Vec<DkgParticipant> = (1..=3)
+            .map(|index| DkgParticipant::new(index, 2, 3).unwrap())
+            .collect();
+        exchange_commitments(&mut participants);
+
+        for sender in 0..3 {
+            for receiver in 0..3 {
+                if sender == receiver {
+                    continue;
+                }
+                let share = participants[sender]
+                    .share_for(participants[receiver].index())
+                    .unwrap();
+                let mut share = DkgShare::from_array(&share.to_array()).unwrap();
+
+                // The third participant sends a wrong share to the first one,
+                // which is corrected after the complaint.
+                if sender == 2 && receiver == 0 {
+                    share = participants[sender].share_for(2).unwrap();
+                    share.receiver = 1;
+                    assert!(!participants[receiver].receive_share(&share));
+                } else {
+                    assert!(participants[receiver].receive_share(&share));
+                }
+            }
+        }
+
+        let complaints = exchange_complaints(&mut participants);
+        assert_eq!(complaints.len(), 1);
+        assert_eq!(complaints[0].complainer(), 1);
+        assert_eq!(complaints[0].accused(), 3);
+
+        let outputs: Vec<_> = participants
+            .iter()
+            .map(|participant| participant.finalize().unwrap())
+            .collect();
+        let public_key = outputs[0].public_key().unwrap();
+        for output in outputs.iter() {
+            assert_eq!(output.public_key(), Some(public_key));
+            assert!(output.disqualified().is_empty());
+        }
+
+        // The joint key can be used with threshold decryption.
+        let params = Parameters::new();
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &public_key, plaintext).unwrap();
+
+        let shares: Vec<DecryptionShare> = outputs[1..]
+            .iter()
+            .map(|output| output.secret_share().decryption_share(&capsule))
+            .collect();
+        for share in shares.iter() {
+            let public_share = outputs[0].public_share(share.index()).unwrap();
+            assert!(share.verify(&capsule, &public_share));
+        }
+
+        let plaintext_back = decrypt_original_threshold(&capsule, &shares, &ciphertext).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+    }
+
+    #[test]
+    fn test_dkg_disqualification() {
+        let mut participants: Vec<DkgParticipant> = (1..=3)
+            .map(|index| DkgParticipant::new(index, 2, 3).unwrap())
+            .collect();
+        exchange_commitments(&mut participants);
+
+        // The third participant does not send its shares, and does not answer the complaints.
+        for sender in 0..2 {
+            for receiver in 0..3 {
+                if sender != receiver {
+                    let share = participants[sender]
+                        .share_for(participants[receiver].index())
+                        .unwrap();
+                    assert!(participants[receiver].receive_share(&share));
+                }
+            }
+        }
+        let complaints: Vec<DkgComplaint> = participants[..2]
+            .iter()
+            .flat_map(|participant| participant.complaints().into_vec())
+            .collect();
+        assert_eq!(complaints.len(), 2);
+        for participant in participants.iter_mut() {
+            for complaint in complaints.iter() {
+                assert!(participant.receive_complaint(complaint));
+            }
+        }
+
+        let output0 = participants[0].finalize().unwrap();
+        let output1 = participants[1].finalize().unwrap();
+        assert_eq!(output0.disqualified(), &[3]);
+        assert_eq!(output0.public_key(), output1.public_key());
+
+        assert_eq!(participants[2].finalize(), Err(DkgError::Disqualified));
+    }
+
+    #[test]
+    fn test_dkg_unanswerable_complaints() {
+        let mut participants: Vec<DkgParticipant> = (1..=3)
+            .map(|index| DkgParticipant::new(index, 2, 3).unwrap())
+            .collect();
+
+        // A complaint from a participant whose commitment was not received yet.
+        let complaint = DkgComplaint {
+            complainer: 2,
+            accused: 1,
+        };
+        assert!(!participants[0].receive_complaint(&complaint));
+
+        exchange_commitments(&mut participants);
+        for sender in 0..3 {
+            for receiver in 0..3 {
+                if sender != receiver {
+                    let share = participants[sender]
+                        .share_for(participants[receiver].index())
+                        .unwrap();
+                    assert!(participants[receiver].receive_share(&share));
+                }
+            }
+        }
+
+        // Complaints that cannot be answered are rejected,
+        // and do not lead to the accused participant's disqualification.
+        for &(complainer, accused) in [(0, 1), (4, 1), (1, 1), (2, 0), (2, 4)].iter() {
+            let complaint = DkgComplaint {
+                complainer,
+                accused,
+            };
+            for participant in participants.iter_mut() {
+                assert!(!participant.receive_complaint(&complaint));
+            }
+        }
+
+        let outputs: Vec<_> = participants
+            .iter()
+            .map(|participant| participant.finalize().unwrap())
+            .collect();
+        for output in outputs.iter() {
+            assert!(output.disqualified().is_empty());
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use crate::capsule::Capsule;
+use crate::capsule_frag::{CapsuleFrag, CapsuleFragProof, HashedMetadata, SignedCapsuleFrag};
+use crate::curve::{CurvePoint, CurveScalar, PublicKey, Signature, UncompressedPoint};
+use crate::hashing::HashingMode;
+use crate::key_frag::{KeyFragID, KeyFragSignature, ValidityPeriod};
+use crate::params::Parameters;
+use crate::traits::SerializableToArray;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use typenum::{op, U1};
+
+/// Evidence of an incorrect [`CapsuleFrag`] that can be checked by a third party
+/// with [`verify_evidence()`], without trusting the party that presents it.
+///
+/// The evidence is built from a [`SignedCapsuleFrag`], so that it holds the proxy
+/// to the exact capsule fragment it produced: a capsule fragment modified
+/// by the party presenting the evidence does not carry a valid proxy signature.
+/// The key fragment signature inside the capsule fragment additionally shows
+/// that the proxy was reencrypting on behalf of the delegating party.
+///
+/// Along with the capsule, the fragment, and the public keys, it contains
+/// the precomputed values of the proof of correct reencryption,
+/// so that a verifier with limited resources (e.g. a smart contract) can check the equations
+/// without performing the scalar multiplications.
+/// In the serialized form (see [`SerializableToArray`]) all the points are represented
+/// by their uncompressed affine coordinates (64 bytes each, `x` followed by `y`,
+/// with the identity encoded as all zeros),
+/// and all the scalars are represented explicitly as 32 big-endian bytes.
+///
+/// The evidence identifies the key fragment the capsule fragment was supposedly created with
+/// (see [`CapsuleFrag::kfrag_id()`]), and the proxy that signed it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IncorrectCfragEvidence {
+    capsule: Capsule,
+    signed_cfrag: SignedCapsuleFrag,
+    delegating_pk: PublicKey,
+    receiving_pk: PublicKey,
+    signing_pk: PublicKey,
+    proxy_pk: PublicKey,
+    h: CurveScalar,
+    e_z3: CurvePoint,
+    e1_h: CurvePoint,
+    v_z3: CurvePoint,
+    v1_h: CurvePoint,
+    u_z3: CurvePoint,
+    u1_h: CurvePoint,
+}
+
+type PointSize = <UncompressedPoint as SerializableToArray>::Size;
+type ScalarSize = <CurveScalar as SerializableToArray>::Size;
+type KeyFragIDSize = <KeyFragID as SerializableToArray>::Size;
+type KeyFragSignatureSize = <KeyFragSignature as SerializableToArray>::Size;
+type U32Size = <u32 as SerializableToArray>::Size;
+type ValidityPeriodSize = <ValidityPeriod as SerializableToArray>::Size;
+type HashingModeSize = <HashingMode as SerializableToArray>::Size;
+type SignatureSize = <Signature as SerializableToArray>::Size;
+
+// The parameters and the capsule
+type CapsulePartSize = op!(PointSize + PointSize + PointSize + ScalarSize);
+// The capsule fragment
+type CapsuleFragPartSize = op!(PointSize
+    + PointSize
+    + KeyFragIDSize
+    + PointSize
+    + PointSize
+    + PointSize
+    + PointSize
+    + PointSize
+    + ScalarSize
+    + KeyFragSignatureSize
+    + ScalarSize
+    + U32Size
+    + U32Size
+    + ValidityPeriodSize
+    + HashingModeSize
+    + U1
+    + SignatureSize);
+// The public keys and the precomputed values
+type PrecomputedPartSize = op!(PointSize
+    + PointSize
+    + PointSize
+    + PointSize
+    + ScalarSize
+    + PointSize
+    + PointSize
+    + PointSize
+    + PointSize
+    + PointSize
+    + PointSize);
+
+fn uncompressed(point: &CurvePoint) -> GenericArray<u8, PointSize> {
+    UncompressedPoint(*point).to_array()
+}
+
+fn uncompressed_pk(pk: &PublicKey) -> GenericArray<u8, PointSize> {
+    uncompressed(&pk.to_point())
+}
+
+impl SerializableToArray for IncorrectCfragEvidence {
+    type Size = op!(CapsulePartSize + CapsuleFragPartSize + PrecomputedPartSize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        let capsule = &self.capsule;
+        let cfrag = &self.signed_cfrag.cfrag;
+        let proof = &cfrag.proof;
+        uncompressed(&capsule.params.u)
+            .concat(uncompressed(&capsule.point_e))
+            .concat(uncompressed(&capsule.point_v))
+            .concat(capsule.signature.to_array())
+            .concat(uncompressed(&cfrag.point_e1))
+            .concat(uncompressed(&cfrag.point_v1))
+            .concat(cfrag.kfrag_id.to_array())
+            .concat(uncompressed(&cfrag.precursor))
+            .concat(uncompressed(&proof.point_e2))
+            .concat(uncompressed(&proof.point_v2))
+            .concat(uncompressed(&proof.kfrag_commitment))
+            .concat(uncompressed(&proof.kfrag_pok))
+            .concat(proof.signature.to_array())
+            .concat(proof.kfrag_signature.to_array())
+            .concat(proof.metadata.to_array())
+            .concat(proof.threshold.to_array())
+            .concat(proof.num_kfrags.to_array())
+            .concat(proof.validity.to_array())
+            .concat(proof.hashing_mode.to_array())
+            .concat(proof.public_share_indices.to_array())
+            .concat(self.signed_cfrag.signature.to_array())
+            .concat(uncompressed_pk(&self.delegating_pk))
+            .concat(uncompressed_pk(&self.receiving_pk))
+            .concat(uncompressed_pk(&self.signing_pk))
+            .concat(uncompressed_pk(&self.proxy_pk))
+            .concat(self.h.to_array())
+            .concat(uncompressed(&self.e_z3))
+            .concat(uncompressed(&self.e1_h))
+            .concat(uncompressed(&self.v_z3))
+            .concat(uncompressed(&self.v1_h))
+            .concat(uncompressed(&self.u_z3))
+            .concat(uncompressed(&self.u1_h))
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (u, rest) = UncompressedPoint::take(*arr)?;
+        let (point_e, rest) = UncompressedPoint::take(rest)?;
+        let (point_v, rest) = UncompressedPoint::take(rest)?;
+        let (capsule_signature, rest) = CurveScalar::take(rest)?;
+
+        let (point_e1, rest) = UncompressedPoint::take(rest)?;
+        let (point_v1, rest) = UncompressedPoint::take(rest)?;
+        let (kfrag_id, rest) = KeyFragID::take(rest)?;
+        let (precursor, rest) = UncompressedPoint::take(rest)?;
+        let (point_e2, rest) = UncompressedPoint::take(rest)?;
+        let (point_v2, rest) = UncompressedPoint::take(rest)?;
+        let (kfrag_commitment, rest) = UncompressedPoint::take(rest)?;
+        let (kfrag_pok, rest) = UncompressedPoint::take(rest)?;
+        let (signature, rest) = CurveScalar::take(rest)?;
+        let (kfrag_signature, rest) = KeyFragSignature::take(rest)?;
+        let (metadata, rest) = HashedMetadata::take(rest)?;
+        let (threshold, rest) = u32::take(rest)?;
+        let (num_kfrags, rest) = u32::take(rest)?;
+        let (validity, rest) = ValidityPeriod::take(rest)?;
+        let (hashing_mode, rest) = HashingMode::take(rest)?;
+        if !kfrag_signature.matches_hashing_mode(hashing_mode) {
+            return None;
+        }
+        let (public_share_indices, rest) = bool::take(rest)?;
+        let (proxy_signature, rest) = Signature::take(rest)?;
+
+        let (delegating_pk, rest) = UncompressedPoint::take(rest)?;
+        let (receiving_pk, rest) = UncompressedPoint::take(rest)?;
+        let (signing_pk, rest) = UncompressedPoint::take(rest)?;
+        let (proxy_pk, rest) = UncompressedPoint::take(rest)?;
+        let (h, rest) = CurveScalar::take(rest)?;
+        let (e_z3, rest) = UncompressedPoint::take(rest)?;
+        let (e1_h, rest) = UncompressedPoint::take(rest)?;
+        let (v_z3, rest) = UncompressedPoint::take(rest)?;
+        let (v1_h, rest) = UncompressedPoint::take(rest)?;
+        let (u_z3, rest) = UncompressedPoint::take(rest)?;
+        let u1_h = UncompressedPoint::take_last(rest)?;
+
+        let capsule = Capsule::new_verified(
+            Parameters { u: u.0 },
+            point_e.0,
+            point_v.0,
+            capsule_signature,
+        )?;
+
+        let cfrag = CapsuleFrag {
+            point_e1: point_e1.0,
+            point_v1: point_v1.0,
+            kfrag_id,
+            precursor: precursor.0,
+            proof: CapsuleFragProof {
+                point_e2: point_e2.0,
+                point_v2: point_v2.0,
+                kfrag_commitment: kfrag_commitment.0,
+                kfrag_pok: kfrag_pok.0,
+                signature,
+                kfrag_signature,
+                metadata,
+                threshold,
+                num_kfrags,
+                validity,
+                hashing_mode,
+                public_share_indices,
+            },
+        };
+
+        Some(Self {
+            capsule,
+            signed_cfrag: SignedCapsuleFrag {
+                cfrag,
+                signature: proxy_signature,
+            },
+            delegating_pk: PublicKey::from_point(&delegating_pk.0)?,
+            receiving_pk: PublicKey::from_point(&receiving_pk.0)?,
+            signing_pk: PublicKey::from_point(&signing_pk.0)?,
+            proxy_pk: PublicKey::from_point(&proxy_pk.0)?,
+            h,
+            e_z3: e_z3.0,
+            e1_h: e1_h.0,
+            v_z3: v_z3.0,
+            v1_h: v1_h.0,
+            u_z3: u_z3.0,
+            u1_h: u1_h.0,
+        })
+    }
+}
+
+impl IncorrectCfragEvidence {
+    /// Packages the evidence of the capsule fragment signed by the proxy being incorrect.
+    ///
+    /// The keys are the same as in [`CapsuleFrag::verify()`],
+    /// and `proxy_pk` is the key [`SignedCapsuleFrag::verify()`] succeeds with.
+    /// Returns `None` if the capsule fragment is actually correct,
+    /// if it is not signed by `proxy_pk`,
+    /// or if it was not created with a key fragment signed by `signing_pk`
+    /// (in which case the proxies cannot be held responsible for it).
+    pub fn new(
+        capsule: &Capsule,
+        signed_cfrag: &SignedCapsuleFrag,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        signing_pk: &PublicKey,
+        proxy_pk: &PublicKey,
+    ) -> Option<Self> {
+        let cfrag = &signed_cfrag.cfrag;
+        let u = capsule.params.u;
+        let h = cfrag.challenge(&capsule.params, &capsule.point_e, &capsule.point_v);
+        let z3 = cfrag.proof.signature;
+
+        let evidence = Self {
+            capsule: *capsule,
+            signed_cfrag: signed_cfrag.clone(),
+            delegating_pk: *delegating_pk,
+            receiving_pk: *receiving_pk,
+            signing_pk: *signing_pk,
+            proxy_pk: *proxy_pk,
+            h,
+            e_z3: &capsule.point_e * &z3,
+            e1_h: &cfrag.point_e1 * &h,
+            v_z3: &capsule.point_v * &z3,
+            v1_h: &cfrag.point_v1 * &h,
+            u_z3: &u * &z3,
+            u1_h: &cfrag.proof.kfrag_commitment * &h,
+        };
+
+        if verify_evidence(&evidence) {
+            Some(evidence)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the identifier of the key fragment the incorrect capsule fragment
+    /// was supposedly created with.
+    pub fn kfrag_id(&self) -> KeyFragID {
+        self.signed_cfrag.cfrag.kfrag_id
+    }
+
+    /// Returns the public key of the proxy that signed the incorrect capsule fragment.
+    pub fn proxy_pk(&self) -> PublicKey {
+        self.proxy_pk
+    }
+}
+
+/// Checks the evidence of an incorrect capsule fragment.
+///
+/// Returns `true` if the capsule fragment is signed by the proxy,
+/// was created with a key fragment signed by the delegating party,
+/// and its proof of correct reencryption does not hold.
+/// Returns `false` if any of the precomputed values
+/// differs from the one calculated from the capsule fragment.
+pub fn verify_evidence(evidence: &IncorrectCfragEvidence) -> bool {
+    let capsule = &evidence.capsule;
+    let cfrag = &evidence.signed_cfrag.cfrag;
+    let proof = &cfrag.proof;
+
+    // Otherwise the party presenting the evidence could have modified the fragment.
+    let valid_proxy_signature = evidence.signed_cfrag.verify(&evidence.proxy_pk);
+
+    // Otherwise anyone could fabricate an incorrect fragment.
+    let valid_kfrag_signature = cfrag.valid_kfrag_signature(
+        &evidence.delegating_pk,
+        &evidence.receiving_pk,
+        &evidence.signing_pk,
+    );
+
+    let u = capsule.params.u;
+    let z3 = proof.signature;
+
+    // The precomputed values must match the ones calculated from the fragment.
+    let h = cfrag.challenge(&capsule.params, &capsule.point_e, &capsule.point_v);
+    let consistent_values = h == evidence.h
+        && evidence.e_z3 == &capsule.point_e * &z3
+        && evidence.e1_h == &cfrag.point_e1 * &h
+        && evidence.v_z3 == &capsule.point_v * &z3
+        && evidence.v1_h == &cfrag.point_v1 * &h
+        && evidence.u_z3 == &u * &z3
+        && evidence.u1_h == &proof.kfrag_commitment * &h;
+
+    let correct_reencryption_of_e = evidence.e_z3 == &proof.point_e2 + &evidence.e1_h;
+    let correct_reencryption_of_v = evidence.v_z3 == &proof.point_v2 + &evidence.v1_h;
+    let correct_rk_commitment = evidence.u_z3 == &proof.kfrag_pok + &evidence.u1_h;
+
+    consistent_values
+        && valid_proxy_signature
+        && valid_kfrag_signature
+        && !(correct_reencryption_of_e && correct_reencryption_of_v && correct_rk_commitment)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{verify_evidence, IncorrectCfragEvidence};
+    use crate::capsule_frag::SignedCapsuleFrag;
+    use crate::curve::CurveScalar;
+    use crate::{
+        encrypt, generate_kfrags, reencrypt, reencrypt_signed, Parameters, PublicKey, SecretKey,
+        SerializableToArray,
+    };
+
+    #[test]
+    fn test_incorrect_cfrag_evidence() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let proxy_sk = SecretKey::random();
+        let proxy_pk = PublicKey::from_secret_key(&proxy_sk);
+
+        let (capsule, _ciphertext) = encrypt(&params, &delegating_pk, b"peace at dawn").unwrap();
+
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+
+        let signed_cfrag = reencrypt_signed(&capsule, &kfrags[0], None, &proxy_sk);
+        assert!(IncorrectCfragEvidence::new(
+            &capsule,
+            &signed_cfrag,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            &proxy_pk,
+        )
+        .is_none());
+
+        // The receiving party cannot blame the proxy for a fragment it modified itself
+        let mut forged_cfrag = signed_cfrag.clone();
+        forged_cfrag.cfrag.point_e1 = reencrypt(&capsule, &kfrags[1], None).point_e1;
+        assert!(!forged_cfrag
+            .cfrag
+            .verify(&capsule, &delegating_pk, &receiving_pk, &signing_pk));
+        assert!(IncorrectCfragEvidence::new(
+            &capsule,
+            &forged_cfrag,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            &proxy_pk,
+        )
+        .is_none());
+
+        // A misbehaving proxy signing an incorrect fragment
+        let mut bad_cfrag = signed_cfrag.cfrag().clone();
+        bad_cfrag.point_e1 = capsule.point_e;
+        let bad_signed_cfrag = SignedCapsuleFrag::new(bad_cfrag, &proxy_sk);
+
+        // The fragment cannot be blamed on the proxies if the key fragment signature is wrong
+        assert!(IncorrectCfragEvidence::new(
+            &capsule,
+            &bad_signed_cfrag,
+            &delegating_pk,
+            &receiving_pk,
+            &receiving_pk,
+            &proxy_pk,
+        )
+        .is_none());
+
+        // Or if it is not signed by the given proxy
+        assert!(IncorrectCfragEvidence::new(
+            &capsule,
+            &bad_signed_cfrag,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            &signing_pk,
+        )
+        .is_none());
+
+        let evidence = IncorrectCfragEvidence::new(
+            &capsule,
+            &bad_signed_cfrag,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk,
+            &proxy_pk,
+        )
+        .unwrap();
+        assert!(verify_evidence(&evidence));
+        assert_eq!(evidence.kfrag_id(), kfrags[0].id());
+        assert_eq!(evidence.proxy_pk(), proxy_pk);
+
+        let evidence_arr = evidence.to_array();
+        let evidence_back = IncorrectCfragEvidence::from_array(&evidence_arr).unwrap();
+        assert_eq!(evidence, evidence_back);
+        assert!(verify_evidence(&evidence_back));
+
+        // Tampered precomputed values
+        let mut bad_evidence = evidence.clone();
+        bad_evidence.e_z3 = bad_evidence.e1_h;
+        assert!(!verify_evidence(&bad_evidence));
+
+        let mut bad_evidence = evidence.clone();
+        bad_evidence.h = &bad_evidence.h + &CurveScalar::one();
+        assert!(!verify_evidence(&bad_evidence));
+
+        // Including in the serialized form
+        let bad_evidence_back =
+            IncorrectCfragEvidence::from_array(&bad_evidence.to_array()).unwrap();
+        assert!(!verify_evidence(&bad_evidence_back));
+    }
+}
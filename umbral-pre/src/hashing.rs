@@ -2,9 +2,14 @@ use digest::Digest;
 use generic_array::sequence::Concat;
 use generic_array::GenericArray;
 use sha2::Sha256;
-use typenum::U1;
-
-use crate::curve::{CurvePoint, CurveScalar, PublicKey, SecretKey, Signature};
+use sha3::Keccak256;
+use typenum::{U1, U32};
+
+use crate::curve::{
+    CurvePoint, CurveScalar, PublicKey, RecoverableSignature, SecretKey, Signature,
+    UncompressedPoint,
+};
+use crate::schnorr::{SchnorrMessage, SchnorrSignature};
 use crate::traits::SerializableToArray;
 
 /// Hashes arbitrary data with the given domain separation tag
@@ -56,11 +61,96 @@ pub fn unsafe_hash_to_point(dst: &[u8], data: &[u8]) -> Option<CurvePoint> {
     None
 }
 
-pub(crate) struct ScalarDigest(Sha256);
+/// The hash function and the encoding of the hashed values used in the proofs
+/// of correct reencryption and in the signatures of key fragments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashingMode {
+    /// SHA-256 over compressed points.
+    Sha256,
+    /// Keccak-256 over uncompressed points (64 bytes, `x` followed by `y`)
+    /// and big-endian integers, that is, the same bytes `abi.encodePacked()` produces
+    /// for the respective Solidity types.
+    /// In this mode the ECDSA signatures of key fragments are recoverable,
+    /// which allows one to check the proofs and the signatures on the EVM
+    /// (the latter with `ecrecover`).
+    Keccak256,
+}
+
+impl SerializableToArray for HashingMode {
+    type Size = U1;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        let tag = match self {
+            Self::Sha256 => 0u8,
+            Self::Keccak256 => 1u8,
+        };
+        GenericArray::<u8, Self::Size>::from([tag])
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        match arr.as_slice()[0] {
+            0u8 => Some(Self::Sha256),
+            1u8 => Some(Self::Keccak256),
+            _ => None,
+        }
+    }
+}
+
+// The digests are short-lived values, and boxing the larger state
+// would add an allocation to every hash computation.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+enum ModeDigest {
+    Sha256(Sha256),
+    Keccak256(Keccak256),
+}
+
+impl ModeDigest {
+    fn new(mode: HashingMode) -> Self {
+        match mode {
+            HashingMode::Sha256 => Self::Sha256(Sha256::new()),
+            HashingMode::Keccak256 => Self::Keccak256(Keccak256::new()),
+        }
+    }
+
+    fn hashing_mode(&self) -> HashingMode {
+        match self {
+            Self::Sha256(_) => HashingMode::Sha256,
+            Self::Keccak256(_) => HashingMode::Keccak256,
+        }
+    }
+
+    fn chain(self, bytes: &[u8]) -> Self {
+        match self {
+            Self::Sha256(d) => Self::Sha256(digest::Digest::chain(d, bytes)),
+            Self::Keccak256(d) => Self::Keccak256(digest::Digest::chain(d, bytes)),
+        }
+    }
+
+    fn chain_point(self, point: &CurvePoint) -> Self {
+        match self {
+            Self::Sha256(_) => self.chain(&point.to_array()),
+            Self::Keccak256(_) => self.chain(&UncompressedPoint(*point).to_array()),
+        }
+    }
+
+    fn finalize(self) -> GenericArray<u8, U32> {
+        match self {
+            Self::Sha256(d) => d.finalize(),
+            Self::Keccak256(d) => d.finalize(),
+        }
+    }
+}
+
+pub(crate) struct ScalarDigest(ModeDigest);
 
 impl ScalarDigest {
     pub fn new() -> Self {
-        Self(Sha256::new())
+        Self::new_with_mode(HashingMode::Sha256)
+    }
+
+    pub fn new_with_mode(mode: HashingMode) -> Self {
+        Self(ModeDigest::new(mode))
     }
 
     pub fn new_with_dst(bytes: &[u8]) -> Self {
@@ -68,7 +158,7 @@ impl ScalarDigest {
     }
 
     fn chain_impl(self, bytes: &[u8]) -> Self {
-        Self(digest::Digest::chain(self.0, bytes))
+        Self(self.0.chain(bytes))
     }
 
     pub fn chain_bytes<T: AsRef<[u8]>>(self, bytes: T) -> Self {
@@ -76,7 +166,7 @@ impl ScalarDigest {
     }
 
     pub fn chain_point(self, point: &CurvePoint) -> Self {
-        self.chain_impl(&point.to_array())
+        Self(self.0.chain_point(point))
     }
 
     pub fn chain_points(self, points: &[CurvePoint]) -> Self {
@@ -89,19 +179,28 @@ impl ScalarDigest {
 
     pub fn finalize(self) -> CurveScalar {
         // TODO (#35): use the standard method when it is available in RustCrypto.
-        CurveScalar::from_digest(self.0)
+        // The digest is reduced modulo the curve order
+        // (`uint256(keccak256(...)) % n` in Solidity).
+        match self.0 {
+            ModeDigest::Sha256(d) => CurveScalar::from_digest(d),
+            ModeDigest::Keccak256(d) => CurveScalar::from_digest(d),
+        }
     }
 }
 
-pub(crate) struct SignatureDigest(Sha256);
+pub(crate) struct SignatureDigest(ModeDigest);
 
 impl SignatureDigest {
     pub fn new() -> Self {
-        Self(Sha256::new())
+        Self::new_with_mode(HashingMode::Sha256)
+    }
+
+    pub fn new_with_mode(mode: HashingMode) -> Self {
+        Self(ModeDigest::new(mode))
     }
 
     fn chain_impl(self, bytes: &[u8]) -> Self {
-        Self(digest::Digest::chain(self.0, bytes))
+        Self(self.0.chain(bytes))
     }
 
     pub fn chain_bytes<T: AsRef<[u8]>>(self, bytes: T) -> Self {
@@ -109,23 +208,64 @@ impl SignatureDigest {
     }
 
     pub fn chain_point(self, point: &CurvePoint) -> Self {
-        self.chain_impl(&point.to_array())
+        Self(self.0.chain_point(point))
     }
 
     pub fn chain_pubkey(self, pk: &PublicKey) -> Self {
-        self.chain_impl(&pk.to_array())
+        self.chain_point(&pk.to_point())
     }
 
     pub fn chain_bool(self, val: bool) -> Self {
         self.chain_impl(&[val as u8])
     }
 
+    pub fn chain_u32(self, val: u32) -> Self {
+        self.chain_impl(&val.to_be_bytes())
+    }
+
     pub fn sign(self, sk: &SecretKey) -> Signature {
-        sk.sign_digest(self.0)
+        match self.0 {
+            ModeDigest::Sha256(d) => sk.sign_digest(d),
+            ModeDigest::Keccak256(d) => sk.sign_digest(d),
+        }
     }
 
     pub fn verify(self, pk: &PublicKey, signature: &Signature) -> bool {
-        pk.verify_digest(self.0, signature)
+        match self.0 {
+            ModeDigest::Sha256(d) => pk.verify_digest(d, signature),
+            ModeDigest::Keccak256(d) => pk.verify_digest(d, signature),
+        }
+    }
+
+    pub fn hashing_mode(&self) -> HashingMode {
+        self.0.hashing_mode()
+    }
+
+    pub fn sign_recoverable(self, sk: &SecretKey) -> RecoverableSignature {
+        match self.0 {
+            ModeDigest::Sha256(d) => sk.sign_digest_recoverable(d),
+            ModeDigest::Keccak256(d) => sk.sign_digest_recoverable(d),
+        }
+    }
+
+    pub fn verify_recoverable(self, pk: &PublicKey, signature: &RecoverableSignature) -> bool {
+        match self.0 {
+            ModeDigest::Sha256(d) => pk.verify_digest_recoverable(d, signature),
+            ModeDigest::Keccak256(d) => pk.verify_digest_recoverable(d, signature),
+        }
+    }
+
+    /// Returns the digest as a message to be signed with a Schnorr signature.
+    pub fn schnorr_message(self) -> SchnorrMessage {
+        self.0.finalize()
+    }
+
+    pub fn sign_schnorr(self, sk: &SecretKey) -> SchnorrSignature {
+        SchnorrSignature::new(sk, &self.schnorr_message())
+    }
+
+    pub fn verify_schnorr(self, pk: &PublicKey, signature: &SchnorrSignature) -> bool {
+        signature.verify(pk, &self.schnorr_message())
     }
 }
 
@@ -160,10 +300,20 @@ impl BytesDigest {
 mod tests {
 
     use super::{
-        unsafe_hash_to_point, BytesDigest, BytesDigestOutputSize, ScalarDigest, SignatureDigest,
+        unsafe_hash_to_point, BytesDigest, BytesDigestOutputSize, HashingMode, ScalarDigest,
+        SignatureDigest,
     };
-    use crate::curve::{CurvePoint, CurveScalar, PublicKey, SecretKey, Signature};
+    use crate::curve::{
+        CurvePoint, CurveScalar, PublicKey, SecretKey, Signature, UncompressedPoint,
+    };
+    use crate::traits::SerializableToArray;
+    use alloc::vec::Vec;
+    use core::convert::TryFrom;
+    use digest::Digest;
+    use elliptic_curve::sec1::ToEncodedPoint;
     use generic_array::GenericArray;
+    use k256::ecdsa::recoverable;
+    use sha3::Keccak256;
 
     #[test]
     fn test_unsafe_hash_to_point() {
@@ -248,6 +398,52 @@ mod tests {
             .verify(&signing_pk, &signature);
 
         assert!(!different_values_same_key);
+
+        let different_mode = SignatureDigest::new_with_mode(HashingMode::Keccak256)
+            .chain_point(&p2)
+            .chain_bytes(&bytes)
+            .chain_bool(b)
+            .chain_pubkey(&pk)
+            .verify(&signing_pk, &signature);
+
+        assert!(!different_mode);
+    }
+
+    #[test]
+    fn test_keccak_signature_digest() {
+        let p1 = CurvePoint::generator();
+        let bytes = b"asdfghjk";
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let signature = SignatureDigest::new_with_mode(HashingMode::Keccak256)
+            .chain_point(&p1)
+            .chain_bytes(&bytes)
+            .chain_u32(1)
+            .sign_recoverable(&signing_sk);
+
+        // The same message, packed the way `abi.encodePacked()` does it
+        let mut message = Vec::new();
+        message.extend(UncompressedPoint(p1).to_array());
+        message.extend(bytes);
+        message.extend(&[0, 0, 0, 1]);
+
+        let recoverable_signature =
+            recoverable::Signature::try_from(signature.to_array().as_slice()).unwrap();
+        let recovered_key = recoverable_signature
+            .recover_verify_key_from_digest(Keccak256::new().chain(&message))
+            .unwrap();
+        assert_eq!(
+            recovered_key.to_encoded_point(false).as_bytes()[1..],
+            UncompressedPoint(signing_pk.to_point()).to_array()[..]
+        );
+
+        assert!(SignatureDigest::new_with_mode(HashingMode::Keccak256)
+            .chain_point(&p1)
+            .chain_bytes(&bytes)
+            .chain_u32(1)
+            .verify_recoverable(&signing_pk, &signature));
     }
 
     #[test]
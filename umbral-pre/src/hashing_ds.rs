@@ -3,10 +3,14 @@
 
 use generic_array::GenericArray;
 
-use crate::curve::{CurvePoint, CurveScalar};
+use crate::curve::{CurvePoint, CurveScalar, PublicKey};
 use crate::hashing::{BytesDigest, BytesDigestOutputSize, ScalarDigest};
 use crate::key_frag::KeyFragID;
+use crate::traits::SerializableToArray;
 
+// The share index depends on the Diffie-Hellman point of the delegation,
+// so that it is only known to the delegating and the receiving parties,
+// and the proxies cannot reconstruct the re-encryption key without the receiving party.
 // TODO (#39): Ideally this should return a non-zero scalar.
 pub(crate) fn hash_to_polynomial_arg(
     precursor: &CurvePoint,
@@ -22,6 +26,21 @@ pub(crate) fn hash_to_polynomial_arg(
         .finalize()
 }
 
+// The share index for the delegations that opted into public share indices
+// (see `KeyFragOptions::public_share_indices()`):
+// it only depends on the values covered by the key fragment signatures,
+// so that it can be recomputed by anyone holding the fragment.
+// TODO (#39): Ideally this should return a non-zero scalar.
+pub(crate) fn hash_to_public_polynomial_arg(
+    precursor: &CurvePoint,
+    kfrag_id: &KeyFragID,
+) -> CurveScalar {
+    ScalarDigest::new_with_dst(b"PUBLIC_POLYNOMIAL_ARG")
+        .chain_point(precursor)
+        .chain_bytes(kfrag_id)
+        .finalize()
+}
+
 // TODO (#39): Ideally this should return a non-zero scalar.
 // (when it does, the loop in `KeyFragFactory::new()` can be removed)
 pub(crate) fn hash_to_shared_secret(
@@ -41,3 +60,24 @@ pub(crate) fn hash_metadata(bytes: &[u8]) -> GenericArray<u8, BytesDigestOutputS
         .chain_bytes(bytes)
         .finalize()
 }
+
+pub(crate) fn hash_to_key_tweak(pk: &PublicKey, label: &[u8]) -> CurveScalar {
+    ScalarDigest::new_with_dst(b"KEY_TWEAK")
+        .chain_point(&pk.to_point())
+        .chain_bytes(label)
+        .finalize()
+}
+
+pub(crate) fn hash_to_kfrag_id(
+    precursor: &CurvePoint,
+    dh_point: &CurvePoint,
+    index: u32,
+) -> KeyFragID {
+    KeyFragID::new(
+        BytesDigest::new_with_dst(b"KFRAG_ID")
+            .chain_bytes(&precursor.to_array())
+            .chain_bytes(&dh_point.to_array())
+            .chain_bytes(&index.to_array())
+            .finalize(),
+    )
+}
@@ -0,0 +1,155 @@
+//! Hybrid encapsulation combining the Umbral capsule with a post-quantum KEM.
+
+use crate::capsule::Capsule;
+use crate::curve::CurvePoint;
+use crate::dem::{kdf, KdfSize};
+use crate::traits::SerializableToArray;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use generic_array::GenericArray;
+use typenum::Unsigned;
+
+/// The result of [`PostQuantumEncapsulator::encapsulate()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PqEncapsulation {
+    /// The KEM ciphertext, to be passed to [`PostQuantumDecapsulator::decapsulate()`].
+    pub ciphertext: Box<[u8]>,
+    /// The shared secret encapsulated in the ciphertext.
+    pub shared_secret: Box<[u8]>,
+}
+
+/// The encapsulating side of a post-quantum KEM (e.g. ML-KEM),
+/// to be implemented for its public key type.
+///
+/// This crate only defines the interface and does not include a post-quantum KEM,
+/// since none is available among its dependencies;
+/// the implementation (for example, a wrapper around an ML-KEM crate)
+/// is to be provided by the user.
+pub trait PostQuantumEncapsulator {
+    /// Generates a random shared secret and returns it
+    /// along with its encapsulation (the KEM ciphertext).
+    ///
+    /// The shared secret must be uniformly random,
+    /// as is the case for the IND-CCA2 secure KEMs like ML-KEM.
+    fn encapsulate(&self) -> Option<PqEncapsulation>;
+}
+
+/// The decapsulating side of a post-quantum KEM (e.g. ML-KEM),
+/// to be implemented for its secret key type.
+pub trait PostQuantumDecapsulator {
+    /// Recovers the shared secret from the KEM ciphertext
+    /// created by [`PostQuantumEncapsulator::encapsulate()`].
+    fn decapsulate(&self, ciphertext: &[u8]) -> Option<Box<[u8]>>;
+}
+
+/// The result of hybrid encapsulation (see [`encrypt_hybrid()`](`crate::encrypt_hybrid()`)):
+/// an Umbral [`Capsule`] along with the ciphertext of a post-quantum KEM.
+///
+/// The DEM key is derived from both shared secrets,
+/// so the plaintext stays protected as long as either of the KEMs is secure.
+///
+/// Only the [`Capsule`] part can be reencrypted by proxies.
+/// The post-quantum KEM has no such property, so its ciphertext is passed to the delegatee as is,
+/// and the delegatee has to obtain the decapsulation capability separately
+/// (for example, by the delegator running the post-quantum KEM to the delegatee's key
+/// over the shared secret, or by the data source encapsulating to the delegatee directly).
+/// If that capability is not delegated, the delegatee cannot decrypt.
+///
+/// Since its size depends on the post-quantum KEM, it is serialized with
+/// [`to_bytes()`](`Self::to_bytes()`) instead of [`SerializableToArray`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HybridCapsule {
+    capsule: Capsule,
+    pq_ciphertext: Box<[u8]>,
+}
+
+type CapsuleSize = <Capsule as SerializableToArray>::Size;
+
+impl HybridCapsule {
+    pub(crate) fn new(capsule: Capsule, pq_ciphertext: &[u8]) -> Self {
+        Self {
+            capsule,
+            pq_ciphertext: pq_ciphertext.into(),
+        }
+    }
+
+    /// Returns the Umbral part of the encapsulation, to be passed to the proxies for reencryption.
+    pub fn capsule(&self) -> &Capsule {
+        &self.capsule
+    }
+
+    /// Returns the ciphertext of the post-quantum KEM.
+    pub fn pq_ciphertext(&self) -> &[u8] {
+        &self.pq_ciphertext
+    }
+
+    /// Derives the DEM key seed from the shared secrets of both KEMs,
+    /// binding it to the whole encapsulation.
+    pub(crate) fn combine_key_seeds(
+        &self,
+        umbral_seed: &CurvePoint,
+        pq_secret: &[u8],
+    ) -> GenericArray<u8, KdfSize> {
+        let mut seed = Vec::<u8>::new();
+        seed.extend_from_slice(&umbral_seed.to_array());
+        seed.extend_from_slice(pq_secret);
+
+        let mut info = Vec::<u8>::new();
+        info.extend_from_slice(b"HYBRID_KEM");
+        info.extend_from_slice(&self.to_bytes());
+
+        kdf(&seed, None, Some(&info))
+    }
+
+    /// Serializes the encapsulation.
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        let mut result = Vec::<u8>::new();
+        result.extend_from_slice(&self.capsule.to_array());
+        result.extend_from_slice(&self.pq_ciphertext);
+        result.into_boxed_slice()
+    }
+
+    /// Attempts to produce the encapsulation back from the serialized form.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Option<Self> {
+        let bytes = bytes.as_ref();
+        let capsule_size = CapsuleSize::to_usize();
+
+        if bytes.len() <= capsule_size {
+            return None;
+        }
+
+        let (capsule_bytes, pq_ciphertext) = bytes.split_at(capsule_size);
+        let capsule = Capsule::from_bytes(capsule_bytes)?;
+
+        Some(Self::new(capsule, pq_ciphertext))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use typenum::Unsigned;
+
+    use super::{CapsuleSize, HybridCapsule};
+    use crate::capsule::Capsule;
+    use crate::{Parameters, PublicKey, SecretKey};
+
+    #[test]
+    fn test_serialize() {
+        let params = Parameters::new();
+        let pk = PublicKey::from_secret_key(&SecretKey::random());
+        let (capsule, _key_seed) = Capsule::from_pubkey(&params, &pk);
+
+        let hybrid_capsule = HybridCapsule::new(capsule, b"post-quantum ciphertext");
+        let hybrid_capsule_back = HybridCapsule::from_bytes(&hybrid_capsule.to_bytes()).unwrap();
+        assert_eq!(hybrid_capsule, hybrid_capsule_back);
+
+        // The post-quantum part cannot be empty
+        assert!(
+            HybridCapsule::from_bytes(&hybrid_capsule.to_bytes()[..CapsuleSize::to_usize()])
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use core::fmt;
+
+use generic_array::GenericArray;
+use hkdf::Hkdf;
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use typenum::U64;
+use zeroize::Zeroize;
+
+use crate::curve::{CurvePoint, PublicKey, SecretKey};
+use crate::hashing::ScalarDigest;
+use crate::hashing_ds::hash_to_key_tweak;
+use crate::traits::SerializableToArray;
+
+type SecretKeyFactorySeedSize = U64;
+
+/// A factory deterministically deriving [`SecretKey`] objects from a master seed
+/// and arbitrary byte labels.
+///
+/// Backing up the seed (in the serialized form of the factory) is enough to recreate
+/// all the keys derived from it, e.g. the delegating keys for different policies.
+/// The seed is zeroized when the factory is dropped.
+#[derive(Clone)]
+pub struct SecretKeyFactory(GenericArray<u8, SecretKeyFactorySeedSize>);
+
+impl SecretKeyFactory {
+    /// Creates a factory with a random seed using the default RNG.
+    pub fn random() -> Self {
+        let mut seed = GenericArray::<u8, SecretKeyFactorySeedSize>::default();
+        OsRng.fill_bytes(&mut seed);
+        Self(seed)
+    }
+
+    /// Derives a secret key for the given label.
+    ///
+    /// The same seed and label always produce the same key,
+    /// and the keys for different labels are independent.
+    pub fn secret_key_by_label(&self, label: &[u8]) -> SecretKey {
+        let hk = Hkdf::<Sha256>::new(None, &self.0);
+
+        let mut info = b"KEY_DERIVATION/".to_vec();
+        info.extend(label);
+
+        let mut key_seed = GenericArray::<u8, SecretKeyFactorySeedSize>::default();
+        // We can only get an error here if the output is too large, and it's known at compile-time.
+        hk.expand(&info, &mut key_seed).unwrap();
+
+        // The counter is only needed in the unlikely case of the hash being zero,
+        // so that the result is still deterministic.
+        let mut i = 0u32;
+        let scalar = loop {
+            let scalar = ScalarDigest::new_with_dst(&info)
+                .chain_bytes(key_seed)
+                .chain_bytes(i.to_be_bytes())
+                .finalize();
+            if !scalar.is_zero() {
+                break scalar;
+            }
+            i += 1;
+        };
+        key_seed.zeroize();
+
+        SecretKey::from_scalar(&scalar)
+    }
+}
+
+impl PartialEq for SecretKeyFactory {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl fmt::Debug for SecretKeyFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Not showing the seed.
+        f.write_str("SecretKeyFactory")
+    }
+}
+
+impl Drop for SecretKeyFactory {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl SerializableToArray for SecretKeyFactory {
+    type Size = SecretKeyFactorySeedSize;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.0
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        Some(Self(*arr))
+    }
+}
+
+impl SecretKey {
+    /// Derives a secret key for the given label, matching the public key derived
+    /// with [`PublicKey::derive()`] from the public key of this one.
+    ///
+    /// **The derivation is not hardened**: anyone knowing the original public key,
+    /// the label, and the derived secret key can recover the original secret key,
+    /// and with it every other key derived from it.
+    /// Use [`SecretKeyFactory`] when the derived keys are to be shared.
+    ///
+    /// In particular, this applies to using the derived key as the delegating key
+    /// in [`generate_kfrags()`](`crate::generate_kfrags()`).
+    /// The receiving party colluding with `threshold` proxies of a single delegation
+    /// can recover the derived secret key (see [`KeyFrag`](`crate::KeyFrag`)),
+    /// and therefore the original one, gaining access to the data under all the labels.
+    /// If that is not acceptable, delegate with keys created by [`SecretKeyFactory`] instead.
+    pub fn derive(&self, label: &[u8]) -> SecretKey {
+        let pk = PublicKey::from_secret_key(self);
+        let tweak = hash_to_key_tweak(&pk, label);
+        // The result is zero with a negligible probability.
+        SecretKey::from_scalar(&(&self.to_secret_scalar() + &tweak))
+    }
+}
+
+impl PublicKey {
+    /// Derives a public key for the given label, without knowing the secret key.
+    ///
+    /// This allows an encrypting party that only knows the delegating party's public key
+    /// to encrypt for a separate key for each label;
+    /// the matching secret key is obtained with [`SecretKey::derive()`],
+    /// and can be used for decryption and delegation as any other one.
+    ///
+    /// Note that the derivation is not hardened, so a leak of a single derived secret key
+    /// compromises the original one; see [`SecretKey::derive()`] for the implications
+    /// of delegating with the derived keys.
+    pub fn derive(&self, label: &[u8]) -> PublicKey {
+        let tweak = hash_to_key_tweak(self, label);
+        let point = &self.to_point() + &(&CurvePoint::generator() * &tweak);
+        // The result is the identity with a negligible probability.
+        PublicKey::from_point(&point).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::SecretKeyFactory;
+    use alloc::vec::Vec;
+
+    use crate::{
+        decrypt_original, decrypt_reencrypted, encrypt, generate_kfrags, reencrypt, CapsuleFrag,
+        Parameters, PublicKey, SecretKey, SerializableToArray,
+    };
+
+    #[test]
+    fn test_secret_key_factory() {
+        let factory = SecretKeyFactory::random();
+        let sk1 = factory.secret_key_by_label(b"foo");
+        let sk2 = factory.secret_key_by_label(b"foo");
+        let sk3 = factory.secret_key_by_label(b"bar");
+        assert_eq!(sk1, sk2);
+        assert_ne!(sk1, sk3);
+
+        let factory_back = SecretKeyFactory::from_array(&factory.to_array()).unwrap();
+        assert_eq!(factory, factory_back);
+        assert_eq!(factory_back.secret_key_by_label(b"foo"), sk1);
+
+        let other_factory = SecretKeyFactory::random();
+        assert_ne!(factory, other_factory);
+        assert_ne!(other_factory.secret_key_by_label(b"foo"), sk1);
+    }
+
+    #[test]
+    fn test_derived_keys_delegation() {
+        let params = Parameters::new();
+        let factory = SecretKeyFactory::random();
+
+        let delegating_sk = factory.secret_key_by_label(b"policy-1");
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+        let receiving_pk = PublicKey::from_secret_key(&SecretKey::random());
+
+        let (capsule, _ciphertext) = encrypt(&params, &delegating_pk, b"peace at dawn").unwrap();
+
+        // The key is recreated from the backup to generate the fragments
+        let restored = SecretKeyFactory::from_array(&factory.to_array()).unwrap();
+        let kfrags = generate_kfrags(
+            &params,
+            &restored.secret_key_by_label(b"policy-1"),
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        assert!(kfrags[0].verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk)));
+        assert!(reencrypt(&capsule, &kfrags[0], None).verify(
+            &capsule,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk
+        ));
+    }
+
+    #[test]
+    fn test_label_derivation() {
+        let params = Parameters::new();
+
+        let alice_sk = SecretKey::random();
+        let alice_pk = PublicKey::from_secret_key(&alice_sk);
+
+        let label_sk = alice_sk.derive(b"label");
+        let label_pk = alice_pk.derive(b"label");
+        assert_eq!(PublicKey::from_secret_key(&label_sk), label_pk);
+        assert_ne!(label_pk, alice_pk);
+        assert_ne!(alice_pk.derive(b"other label"), label_pk);
+
+        // The encrypting party only knows Alice's public key
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &label_pk, plaintext).unwrap();
+
+        assert!(decrypt_original(&alice_sk, &capsule, &ciphertext).is_none());
+        let plaintext_alice = decrypt_original(&label_sk, &capsule, &ciphertext).unwrap();
+        assert_eq!(&plaintext_alice as &[u8], plaintext);
+
+        let signing_sk = SecretKey::random();
+        let bob_sk = SecretKey::random();
+        let bob_pk = PublicKey::from_secret_key(&bob_sk);
+
+        let kfrags = generate_kfrags(&params, &label_sk, &bob_pk, &signing_sk, 2, 3, true, true);
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+
+        let plaintext_bob =
+            decrypt_reencrypted(&bob_sk, &label_pk, &capsule, &cfrags, &ciphertext).unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+}
@@ -0,0 +1,333 @@
+//! A password-protected storage format for secret keys.
+
+use crate::curve::SecretKey;
+use crate::dem::{KdfSize, UmbralDEM};
+use crate::traits::SerializableToArray;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+
+use generic_array::GenericArray;
+use rand_core::{OsRng, RngCore};
+use scrypt::{scrypt, Params};
+use zeroize::Zeroize;
+
+const MAGIC: &[u8; 4] = b"UMBK";
+const VERSION: u8 = 1;
+const SALT_SIZE: usize = 16;
+// magic, version, key type, scrypt `log_n`, `r` and `p`, salt
+const HEADER_SIZE: usize = 4 + 1 + 1 + 1 + 4 + 4 + SALT_SIZE;
+
+/// Bounds on the scrypt parameters accepted when restoring a key
+/// with [`SecretKey::from_encrypted_bytes_with_limits()`],
+/// so that a malicious file could not make us spend an arbitrary amount of memory and time.
+///
+/// Scrypt takes `128 * r * 2^log_n` bytes of memory, and `p` times that amount of work.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyFileLimits {
+    max_log_n: u8,
+    max_r: u32,
+    max_p: u32,
+}
+
+impl KeyFileLimits {
+    /// Creates the bounds with the given maximum values of the scrypt parameters.
+    pub fn new(max_log_n: u8, max_r: u32, max_p: u32) -> Self {
+        Self {
+            max_log_n,
+            max_r,
+            max_p,
+        }
+    }
+
+    fn allow(&self, log_n: u8, r: u32, p: u32) -> bool {
+        log_n <= self.max_log_n && r <= self.max_r && p <= self.max_p
+    }
+}
+
+impl Default for KeyFileLimits {
+    /// The bounds used by [`SecretKey::from_encrypted_bytes()`],
+    /// slightly above the parameters [`SecretKey::to_encrypted_bytes()`] uses
+    /// (at most 128 * 8 * 2^18 bytes = 256 MiB of memory).
+    fn default() -> Self {
+        Self::new(18, 8, 1)
+    }
+}
+
+/// The role of a secret key, recorded in its encrypted form
+/// (see [`SecretKey::to_encrypted_bytes()`]).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SecretKeyType {
+    /// A key the data is encrypted to, and which is used to create key fragments.
+    Delegating,
+    /// A key signing the key fragments.
+    Signing,
+    /// A key receiving the reencrypted data.
+    Receiving,
+}
+
+impl SecretKeyType {
+    fn to_tag(self) -> u8 {
+        match self {
+            Self::Delegating => 1,
+            Self::Signing => 2,
+            Self::Receiving => 3,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(Self::Delegating),
+            2 => Some(Self::Signing),
+            3 => Some(Self::Receiving),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that can happen when restoring a secret key from its encrypted form.
+#[derive(Debug, PartialEq)]
+pub enum KeyFileError {
+    /// The data is too short or does not start with the expected magic bytes.
+    InvalidFormat,
+    /// The format version is not supported by this library.
+    UnsupportedVersion(u8),
+    /// The key type tag is not recognized.
+    UnknownKeyType(u8),
+    /// The password-based KDF parameters are invalid or exceed the allowed bounds.
+    InvalidKdfParameters,
+    /// The password is wrong, or the data was tampered with.
+    DecryptionFailed,
+}
+
+impl fmt::Display for KeyFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => write!(f, "Invalid key file format"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported key file version: {}", version)
+            }
+            Self::UnknownKeyType(tag) => write!(f, "Unknown key type: {}", tag),
+            Self::InvalidKdfParameters => write!(f, "Invalid key derivation parameters"),
+            Self::DecryptionFailed => {
+                write!(f, "Decryption failed: wrong password or corrupted data")
+            }
+        }
+    }
+}
+
+fn derive_key(password: &[u8], salt: &[u8], params: &Params) -> GenericArray<u8, KdfSize> {
+    let mut key = GenericArray::<u8, KdfSize>::default();
+    // We can only get an error here if `KdfSize` is zero or too large,
+    // and it's known at compile-time.
+    scrypt(password, salt, params, &mut key).unwrap();
+    key
+}
+
+impl SecretKey {
+    /// Encrypts the secret key with a key derived from `password`,
+    /// producing a self-contained byte string suitable for storage.
+    ///
+    /// The format consists of a versioned header (carrying `key_type`,
+    /// the scrypt parameters, and a random salt)
+    /// followed by the ChaCha20-Poly1305 encryption of the key, authenticated with the header.
+    /// It can be decrypted with [`from_encrypted_bytes()`](`Self::from_encrypted_bytes()`).
+    pub fn to_encrypted_bytes(&self, password: &[u8], key_type: SecretKeyType) -> Box<[u8]> {
+        self.to_encrypted_bytes_with_params(password, key_type, &Params::recommended())
+    }
+
+    fn to_encrypted_bytes_with_params(
+        &self,
+        password: &[u8],
+        key_type: SecretKeyType,
+        params: &Params,
+    ) -> Box<[u8]> {
+        let mut salt = [0u8; SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+
+        let mut header = Vec::<u8>::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(MAGIC);
+        header.push(VERSION);
+        header.push(key_type.to_tag());
+        header.push(params.log_n());
+        header.extend_from_slice(&params.r().to_be_bytes());
+        header.extend_from_slice(&params.p().to_be_bytes());
+        header.extend_from_slice(&salt);
+
+        let mut key = derive_key(password, &salt, params);
+        let dem = UmbralDEM::new(&key);
+        key.zeroize();
+
+        let mut key_bytes = self.to_array();
+        // We can only get an error here if the plaintext is too large,
+        // and its size is fixed.
+        let ciphertext = dem.encrypt(&key_bytes, &header).unwrap();
+        key_bytes.zeroize();
+
+        let mut result = header;
+        result.extend_from_slice(&ciphertext);
+        result.into_boxed_slice()
+    }
+
+    /// Restores the secret key encrypted with
+    /// [`to_encrypted_bytes()`](`Self::to_encrypted_bytes()`),
+    /// returning it along with its recorded type.
+    ///
+    /// The scrypt parameters recorded in the data are checked against
+    /// the default [`KeyFileLimits`];
+    /// use [`from_encrypted_bytes_with_limits()`](`Self::from_encrypted_bytes_with_limits()`)
+    /// to restore keys encrypted with more expensive parameters.
+    pub fn from_encrypted_bytes(
+        bytes: impl AsRef<[u8]>,
+        password: &[u8],
+    ) -> Result<(Self, SecretKeyType), KeyFileError> {
+        Self::from_encrypted_bytes_with_limits(bytes, password, &KeyFileLimits::default())
+    }
+
+    /// Same as [`from_encrypted_bytes()`](`Self::from_encrypted_bytes()`),
+    /// but checks the scrypt parameters against the given `limits`.
+    ///
+    /// Only raise the limits for data coming from a trusted source.
+    pub fn from_encrypted_bytes_with_limits(
+        bytes: impl AsRef<[u8]>,
+        password: &[u8],
+        limits: &KeyFileLimits,
+    ) -> Result<(Self, SecretKeyType), KeyFileError> {
+        let bytes = bytes.as_ref();
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(KeyFileError::InvalidFormat);
+        }
+
+        let (header, ciphertext) = bytes.split_at(HEADER_SIZE);
+        if header[4] != VERSION {
+            return Err(KeyFileError::UnsupportedVersion(header[4]));
+        }
+        let key_type =
+            SecretKeyType::from_tag(header[5]).ok_or(KeyFileError::UnknownKeyType(header[5]))?;
+
+        let log_n = header[6];
+        // The slices have the correct size by construction.
+        let r = u32::from_be_bytes(header[7..11].try_into().unwrap());
+        let p = u32::from_be_bytes(header[11..15].try_into().unwrap());
+        if !limits.allow(log_n, r, p) {
+            return Err(KeyFileError::InvalidKdfParameters);
+        }
+        let params = Params::new(log_n, r, p).map_err(|_| KeyFileError::InvalidKdfParameters)?;
+        let salt = &header[15..];
+
+        let mut key = derive_key(password, salt, &params);
+        let dem = UmbralDEM::new(&key);
+        key.zeroize();
+
+        let mut key_bytes = dem
+            .decrypt(ciphertext, header)
+            .ok_or(KeyFileError::DecryptionFailed)?;
+        let sk = Self::from_bytes(&key_bytes);
+        key_bytes.zeroize();
+
+        // An authenticated ciphertext can only contain a valid key.
+        let sk = sk.ok_or(KeyFileError::InvalidFormat)?;
+        Ok((sk, key_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use scrypt::Params;
+
+    use super::{KeyFileError, KeyFileLimits, SecretKeyType};
+    use crate::SecretKey;
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let sk = SecretKey::random();
+        // Cheap parameters to keep the test fast
+        let params = Params::new(4, 8, 1).unwrap();
+        let password = b"correct horse battery staple";
+
+        let encrypted =
+            sk.to_encrypted_bytes_with_params(password, SecretKeyType::Signing, &params);
+
+        let (sk_back, key_type) = SecretKey::from_encrypted_bytes(&encrypted, password).unwrap();
+        assert_eq!(sk_back, sk);
+        assert_eq!(key_type, SecretKeyType::Signing);
+
+        // The salt is random
+        let encrypted2 =
+            sk.to_encrypted_bytes_with_params(password, SecretKeyType::Signing, &params);
+        assert_ne!(encrypted, encrypted2);
+
+        assert_eq!(
+            SecretKey::from_encrypted_bytes(&encrypted, b"wrong password"),
+            Err(KeyFileError::DecryptionFailed)
+        );
+
+        // The header is authenticated
+        let mut tampered = encrypted.to_vec();
+        tampered[5] = SecretKeyType::Delegating.to_tag();
+        assert_eq!(
+            SecretKey::from_encrypted_bytes(&tampered, password),
+            Err(KeyFileError::DecryptionFailed)
+        );
+
+        let mut tampered = encrypted.to_vec();
+        tampered[4] = 2;
+        assert_eq!(
+            SecretKey::from_encrypted_bytes(&tampered, password),
+            Err(KeyFileError::UnsupportedVersion(2))
+        );
+
+        let mut tampered = encrypted.to_vec();
+        tampered[5] = 0;
+        assert_eq!(
+            SecretKey::from_encrypted_bytes(&tampered, password),
+            Err(KeyFileError::UnknownKeyType(0))
+        );
+
+        let mut tampered = encrypted.to_vec();
+        tampered[6] = 19;
+        assert_eq!(
+            SecretKey::from_encrypted_bytes(&tampered, password),
+            Err(KeyFileError::InvalidKdfParameters)
+        );
+
+        assert_eq!(
+            SecretKey::from_encrypted_bytes(&encrypted[..10], password),
+            Err(KeyFileError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_encrypted_limits() {
+        let sk = SecretKey::random();
+        let password = b"correct horse battery staple";
+
+        // Exceeds the default bound on `r`
+        let params = Params::new(4, 9, 1).unwrap();
+        let encrypted =
+            sk.to_encrypted_bytes_with_params(password, SecretKeyType::Receiving, &params);
+
+        assert_eq!(
+            SecretKey::from_encrypted_bytes(&encrypted, password),
+            Err(KeyFileError::InvalidKdfParameters)
+        );
+
+        let limits = KeyFileLimits::new(18, 9, 1);
+        let (sk_back, key_type) =
+            SecretKey::from_encrypted_bytes_with_limits(&encrypted, password, &limits).unwrap();
+        assert_eq!(sk_back, sk);
+        assert_eq!(key_type, SecretKeyType::Receiving);
+
+        // Exceeds the default bound on `p`
+        let params = Params::new(4, 8, 2).unwrap();
+        let encrypted =
+            sk.to_encrypted_bytes_with_params(password, SecretKeyType::Receiving, &params);
+        assert_eq!(
+            SecretKey::from_encrypted_bytes(&encrypted, password),
+            Err(KeyFileError::InvalidKdfParameters)
+        );
+    }
+}
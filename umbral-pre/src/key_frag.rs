@@ -1,8 +1,13 @@
+use crate::commitment::PolynomialCommitment;
 use crate::curve::{CurvePoint, CurveScalar};
-use crate::curve::{PublicKey, SecretKey, Signature};
-use crate::hashing::SignatureDigest;
-use crate::hashing_ds::{hash_to_polynomial_arg, hash_to_shared_secret};
+use crate::curve::{PublicKey, RecoverableSignature, SecretKey, Signature};
+use crate::hashing::{HashingMode, SignatureDigest};
+use crate::hashing_ds::{
+    hash_to_polynomial_arg, hash_to_public_polynomial_arg, hash_to_shared_secret,
+};
 use crate::params::Parameters;
+use crate::reencrypted_capsule::ReencryptedCapsule;
+use crate::schnorr::SchnorrSignature;
 use crate::traits::SerializableToArray;
 
 use alloc::boxed::Box;
@@ -15,10 +20,16 @@ use typenum::{op, U1, U32};
 
 type KeyFragIDSize = U32;
 
+/// An identifier of a [`KeyFrag`], shared with all the [`CapsuleFrag`](`crate::CapsuleFrag`)
+/// objects created with it.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub(crate) struct KeyFragID(GenericArray<u8, KeyFragIDSize>);
+pub struct KeyFragID(GenericArray<u8, KeyFragIDSize>);
 
 impl KeyFragID {
+    pub(crate) fn new(bytes: GenericArray<u8, KeyFragIDSize>) -> Self {
+        Self(bytes)
+    }
+
     fn random() -> Self {
         let mut bytes = GenericArray::<u8, KeyFragIDSize>::default();
         OsRng.fill_bytes(&mut bytes);
@@ -44,20 +55,237 @@ impl SerializableToArray for KeyFragID {
     }
 }
 
+/// A period of time a [`KeyFrag`] can be used for reencryption in,
+/// in the units chosen by the application (e.g. UNIX timestamps or epoch numbers).
+/// Both ends of the period are inclusive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValidityPeriod {
+    not_before: u64,
+    not_after: u64,
+}
+
+impl ValidityPeriod {
+    /// Creates a new period; `None` means the period is not bounded from the respective side.
+    ///
+    /// Returns `None` if `not_before` is later than `not_after`.
+    pub fn new(not_before: Option<u64>, not_after: Option<u64>) -> Option<Self> {
+        let not_before = not_before.unwrap_or(u64::MIN);
+        let not_after = not_after.unwrap_or(u64::MAX);
+        if not_before > not_after {
+            return None;
+        }
+        Some(Self {
+            not_before,
+            not_after,
+        })
+    }
+
+    /// Creates a period not bounded from either side.
+    pub fn unbounded() -> Self {
+        Self {
+            not_before: u64::MIN,
+            not_after: u64::MAX,
+        }
+    }
+
+    /// Returns the start of the period, if it is bounded.
+    pub fn not_before(&self) -> Option<u64> {
+        match self.not_before {
+            u64::MIN => None,
+            not_before => Some(not_before),
+        }
+    }
+
+    /// Returns the end of the period, if it is bounded.
+    pub fn not_after(&self) -> Option<u64> {
+        match self.not_after {
+            u64::MAX => None,
+            not_after => Some(not_after),
+        }
+    }
+
+    /// Returns `true` if `now` lies within the period.
+    pub fn contains(&self, now: u64) -> bool {
+        self.not_before <= now && now <= self.not_after
+    }
+}
+
+type U64Size = <u64 as SerializableToArray>::Size;
+
+impl SerializableToArray for ValidityPeriod {
+    type Size = op!(U64Size + U64Size);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.not_before.to_array().concat(self.not_after.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (not_before, rest) = u64::take(*arr)?;
+        let not_after = u64::take_last(rest)?;
+        if not_before > not_after {
+            return None;
+        }
+        Some(Self {
+            not_before,
+            not_after,
+        })
+    }
+}
+
+/// The signature scheme used to sign a [`KeyFrag`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SignatureScheme {
+    /// Randomized ECDSA signatures.
+    Ecdsa,
+    /// Deterministic Schnorr signatures as specified in BIP-340,
+    /// which can be verified in batches (see [`verify_kfrags()`]).
+    Schnorr,
+}
+
+impl SerializableToArray for SignatureScheme {
+    type Size = U1;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        let tag = match self {
+            Self::Ecdsa => 0u8,
+            Self::Schnorr => 1u8,
+        };
+        GenericArray::<u8, Self::Size>::from([tag])
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        match arr.as_slice()[0] {
+            0u8 => Some(Self::Ecdsa),
+            1u8 => Some(Self::Schnorr),
+            _ => None,
+        }
+    }
+}
+
+/// A signature of a key fragment made with one of the supported schemes.
+///
+/// ECDSA signatures are recoverable in the [`HashingMode::Keccak256`] mode,
+/// so that they could be checked with `ecrecover` on the EVM, and plain otherwise.
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyFragSignature {
+    Ecdsa(Signature),
+    RecoverableEcdsa(RecoverableSignature),
+    Schnorr(SchnorrSignature),
+}
+
+type RecoverableSignatureSize = <RecoverableSignature as SerializableToArray>::Size;
+
+impl SerializableToArray for KeyFragSignature {
+    // Non-recoverable signatures are shorter and are padded with a zero byte
+    // to keep the size of the fragments fixed.
+    type Size = op!(U1 + RecoverableSignatureSize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        let padding = GenericArray::<u8, U1>::default();
+        match self {
+            Self::Ecdsa(signature) => GenericArray::<u8, U1>::from([0u8])
+                .concat(signature.to_array())
+                .concat(padding),
+            Self::Schnorr(signature) => GenericArray::<u8, U1>::from([1u8])
+                .concat(signature.to_array())
+                .concat(padding),
+            Self::RecoverableEcdsa(signature) => {
+                GenericArray::<u8, U1>::from([2u8]).concat(signature.to_array())
+            }
+        }
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let tag = arr[0];
+        let rest = GenericArray::<u8, RecoverableSignatureSize>::clone_from_slice(&arr[1..]);
+        match tag {
+            0u8 => {
+                let (signature, padding) = Signature::take(rest)?;
+                if padding[0] != 0 {
+                    return None;
+                }
+                Some(Self::Ecdsa(signature))
+            }
+            1u8 => {
+                let (signature, padding) = SchnorrSignature::take(rest)?;
+                if padding[0] != 0 {
+                    return None;
+                }
+                Some(Self::Schnorr(signature))
+            }
+            2u8 => RecoverableSignature::take_last(rest).map(Self::RecoverableEcdsa),
+            _ => None,
+        }
+    }
+}
+
+impl KeyFragSignature {
+    pub(crate) fn new(digest: SignatureDigest, sk: &SecretKey, scheme: SignatureScheme) -> Self {
+        match (scheme, digest.hashing_mode()) {
+            (SignatureScheme::Ecdsa, HashingMode::Sha256) => Self::Ecdsa(digest.sign(sk)),
+            (SignatureScheme::Ecdsa, HashingMode::Keccak256) => {
+                Self::RecoverableEcdsa(digest.sign_recoverable(sk))
+            }
+            (SignatureScheme::Schnorr, _) => Self::Schnorr(digest.sign_schnorr(sk)),
+        }
+    }
+
+    pub(crate) fn verify(&self, digest: SignatureDigest, pk: &PublicKey) -> bool {
+        match self {
+            Self::Ecdsa(signature) => digest.verify(pk, signature),
+            Self::RecoverableEcdsa(signature) => digest.verify_recoverable(pk, signature),
+            Self::Schnorr(signature) => digest.verify_schnorr(pk, signature),
+        }
+    }
+
+    pub(crate) fn scheme(&self) -> SignatureScheme {
+        match self {
+            Self::Ecdsa(_) | Self::RecoverableEcdsa(_) => SignatureScheme::Ecdsa,
+            Self::Schnorr(_) => SignatureScheme::Schnorr,
+        }
+    }
+
+    /// Returns `true` if the signature is of the kind [`Self::new()`] creates in the given mode.
+    pub(crate) fn matches_hashing_mode(&self, hashing_mode: HashingMode) -> bool {
+        match self {
+            Self::Ecdsa(_) => hashing_mode == HashingMode::Sha256,
+            Self::RecoverableEcdsa(_) => hashing_mode == HashingMode::Keccak256,
+            Self::Schnorr(_) => true,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct KeyFragProof {
     pub(crate) commitment: CurvePoint,
-    signature_for_proxy: Signature,
-    signature_for_bob: Signature,
+    signature_for_proxy: KeyFragSignature,
+    signature_for_bob: KeyFragSignature,
     delegating_key_signed: bool,
     receiving_key_signed: bool,
+    pub(crate) threshold: u32,
+    pub(crate) num_kfrags: u32,
+    pub(crate) validity: ValidityPeriod,
+    pub(crate) hashing_mode: HashingMode,
+    pub(crate) public_share_indices: bool,
 }
 
 type ParametersSize = <Parameters as SerializableToArray>::Size;
-type SignatureSize = <Signature as SerializableToArray>::Size;
+type KeyFragSignatureSize = <KeyFragSignature as SerializableToArray>::Size;
 type ScalarSize = <CurveScalar as SerializableToArray>::Size;
 type PointSize = <CurvePoint as SerializableToArray>::Size;
-type KeyFragProofSize = op!(PointSize + SignatureSize + SignatureSize + U1 + U1);
+type U32Size = <u32 as SerializableToArray>::Size;
+type ValidityPeriodSize = <ValidityPeriod as SerializableToArray>::Size;
+type HashingModeSize = <HashingMode as SerializableToArray>::Size;
+pub(crate) type KeyFragProofSize = op!(PointSize
+    + KeyFragSignatureSize
+    + KeyFragSignatureSize
+    + U1
+    + U1
+    + U32Size
+    + U32Size
+    + ValidityPeriodSize
+    + HashingModeSize
+    + U1);
 
 impl SerializableToArray for KeyFragProof {
     type Size = KeyFragProofSize;
@@ -69,53 +297,92 @@ impl SerializableToArray for KeyFragProof {
             .concat(self.signature_for_bob.to_array())
             .concat(self.delegating_key_signed.to_array())
             .concat(self.receiving_key_signed.to_array())
+            .concat(self.threshold.to_array())
+            .concat(self.num_kfrags.to_array())
+            .concat(self.validity.to_array())
+            .concat(self.hashing_mode.to_array())
+            .concat(self.public_share_indices.to_array())
     }
 
     fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
         let (commitment, rest) = CurvePoint::take(*arr)?;
-        let (signature_for_proxy, rest) = Signature::take(rest)?;
-        let (signature_for_bob, rest) = Signature::take(rest)?;
+        let (signature_for_proxy, rest) = KeyFragSignature::take(rest)?;
+        let (signature_for_bob, rest) = KeyFragSignature::take(rest)?;
         let (delegating_key_signed, rest) = bool::take(rest)?;
-        let receiving_key_signed = bool::take_last(rest)?;
+        let (receiving_key_signed, rest) = bool::take(rest)?;
+        let (threshold, rest) = u32::take(rest)?;
+        let (num_kfrags, rest) = u32::take(rest)?;
+        let (validity, rest) = ValidityPeriod::take(rest)?;
+        let (hashing_mode, rest) = HashingMode::take(rest)?;
+        let public_share_indices = bool::take_last(rest)?;
+        if !signature_for_proxy.matches_hashing_mode(hashing_mode)
+            || !signature_for_bob.matches_hashing_mode(hashing_mode)
+        {
+            return None;
+        }
         Some(Self {
             commitment,
             signature_for_proxy,
             signature_for_bob,
             delegating_key_signed,
             receiving_key_signed,
+            threshold,
+            num_kfrags,
+            validity,
+            hashing_mode,
+            public_share_indices,
         })
     }
 }
 
 impl KeyFragProof {
+    /// `commitment` is the re-encryption key share multiplied by `params.u`,
+    /// which allows the proof to be created without knowing the share itself.
     #[allow(clippy::too_many_arguments)]
-    fn new(
-        params: &Parameters,
+    pub(crate) fn new(
         kfrag_id: &KeyFragID,
-        kfrag_key: &CurveScalar,
+        commitment: &CurvePoint,
         kfrag_precursor: &CurvePoint,
         signing_sk: &SecretKey,
         delegating_pk: &PublicKey,
         receiving_pk: &PublicKey,
         sign_delegating_key: bool,
         sign_receiving_key: bool,
+        threshold: u32,
+        num_kfrags: u32,
+        validity: &ValidityPeriod,
+        hashing_mode: HashingMode,
+        signature_scheme: SignatureScheme,
+        public_share_indices: bool,
     ) -> Self {
-        let commitment = &params.u * kfrag_key;
+        let commitment = *commitment;
 
-        let signature_for_bob = SignatureDigest::new()
+        let mut digest_for_bob = SignatureDigest::new_with_mode(hashing_mode)
             .chain_bytes(kfrag_id)
             .chain_pubkey(delegating_pk)
             .chain_pubkey(receiving_pk)
             .chain_point(&commitment)
             .chain_point(kfrag_precursor)
-            .sign(signing_sk);
+            .chain_u32(threshold)
+            .chain_u32(num_kfrags)
+            .chain_bytes(validity.to_array());
+        // The flag is only included when set,
+        // so that the fragments with private share indices are signed the same way
+        // as before the option was introduced.
+        if public_share_indices {
+            digest_for_bob = digest_for_bob.chain_bool(true);
+        }
+        let signature_for_bob = KeyFragSignature::new(digest_for_bob, signing_sk, signature_scheme);
 
-        let mut digest_for_proxy = SignatureDigest::new()
+        let mut digest_for_proxy = SignatureDigest::new_with_mode(hashing_mode)
             .chain_bytes(kfrag_id)
             .chain_point(&commitment)
             .chain_point(kfrag_precursor)
             .chain_bool(sign_delegating_key)
-            .chain_bool(sign_receiving_key);
+            .chain_bool(sign_receiving_key)
+            .chain_u32(threshold)
+            .chain_u32(num_kfrags)
+            .chain_bytes(validity.to_array());
 
         if sign_delegating_key {
             digest_for_proxy = digest_for_proxy.chain_pubkey(delegating_pk);
@@ -125,7 +392,12 @@ impl KeyFragProof {
             digest_for_proxy = digest_for_proxy.chain_pubkey(receiving_pk);
         }
 
-        let signature_for_proxy = digest_for_proxy.sign(&signing_sk);
+        if public_share_indices {
+            digest_for_proxy = digest_for_proxy.chain_bool(true);
+        }
+
+        let signature_for_proxy =
+            KeyFragSignature::new(digest_for_proxy, signing_sk, signature_scheme);
 
         Self {
             commitment,
@@ -133,18 +405,32 @@ impl KeyFragProof {
             signature_for_bob,
             delegating_key_signed: sign_delegating_key,
             receiving_key_signed: sign_receiving_key,
+            threshold,
+            num_kfrags,
+            validity: *validity,
+            hashing_mode,
+            public_share_indices,
         }
     }
 
-    pub(crate) fn signature_for_bob(&self) -> Signature {
+    pub(crate) fn signature_for_bob(&self) -> KeyFragSignature {
         self.signature_for_bob.clone()
     }
 }
 
 /// A fragment of the encrypting party's key used to create a [`CapsuleFrag`](`crate::CapsuleFrag`).
+///
+/// By default, the share index (the point the share polynomial was evaluated at)
+/// is derived from the Diffie-Hellman point of the delegation,
+/// so it is only known to the delegating and the receiving parties,
+/// and the proxies cannot combine their fragments without the receiving party.
+/// The fragments created with
+/// [`KeyFragOptions::public_share_indices()`] have public share indices instead,
+/// which allows their holders to check them against
+/// a [`PolynomialCommitment`](`crate::PolynomialCommitment`).
 #[derive(Clone, Debug, PartialEq)]
 pub struct KeyFrag {
-    params: Parameters,
+    pub(crate) params: Parameters,
     pub(crate) id: KeyFragID,
     pub(crate) key: CurveScalar,
     pub(crate) precursor: CurvePoint,
@@ -182,34 +468,72 @@ impl SerializableToArray for KeyFrag {
 }
 
 impl KeyFrag {
-    fn new(factory: &KeyFragFactory, sign_delegating_key: bool, sign_receiving_key: bool) -> Self {
+    fn new(
+        factory: &KeyFragFactory,
+        num_kfrags: u32,
+        sign_delegating_key: bool,
+        sign_receiving_key: bool,
+    ) -> Self {
         let kfrag_id = KeyFragID::random();
-
-        // The index of the re-encryption key share (which in Shamir's Secret
-        // Sharing corresponds to x in the tuple (x, f(x)), with f being the
-        // generating polynomial), is used to prevent reconstruction of the
-        // re-encryption key without Bob's intervention
-        let share_index = hash_to_polynomial_arg(
-            &factory.precursor,
-            &factory.bob_pubkey_point,
-            &factory.dh_point,
-            &kfrag_id,
-        );
+        let share_index = factory.share_index(&kfrag_id);
 
         // The re-encryption key share is the result of evaluating the generating
         // polynomial for the index value
         let rk = poly_eval(&factory.coefficients, &share_index);
 
+        Self::from_share(
+            factory,
+            kfrag_id,
+            rk,
+            factory.coefficients.len() as u32,
+            num_kfrags,
+            sign_delegating_key,
+            sign_receiving_key,
+        )
+    }
+
+    /// Adds the value of the update polynomial (with the zero constant term)
+    /// to the re-encryption key share, keeping the rest of the fragment the same.
+    fn refreshed(&self, factory: &KeyFragFactory) -> Self {
+        let update = poly_eval(&factory.coefficients, &factory.share_index(&self.id));
+        let rk = &self.key + &update;
+
+        Self::from_share(
+            factory,
+            self.id,
+            rk,
+            self.proof.threshold,
+            self.proof.num_kfrags,
+            self.proof.delegating_key_signed,
+            self.proof.receiving_key_signed,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn from_share(
+        factory: &KeyFragFactory,
+        kfrag_id: KeyFragID,
+        rk: CurveScalar,
+        threshold: u32,
+        num_kfrags: u32,
+        sign_delegating_key: bool,
+        sign_receiving_key: bool,
+    ) -> Self {
         let proof = KeyFragProof::new(
-            &factory.params,
             &kfrag_id,
-            &rk,
+            &(&factory.params.u * &rk),
             &factory.precursor,
             &factory.signing_sk,
             &factory.delegating_pk,
             &factory.receiving_pk,
             sign_delegating_key,
             sign_receiving_key,
+            threshold,
+            num_kfrags,
+            &factory.validity,
+            factory.hashing_mode,
+            factory.signature_scheme,
+            factory.public_share_indices,
         );
 
         Self {
@@ -233,30 +557,45 @@ impl KeyFrag {
         delegating_pk: Option<&PublicKey>,
         receiving_pk: Option<&PublicKey>,
     ) -> bool {
+        let digest = match self.digest_for_proxy(delegating_pk, receiving_pk) {
+            Some(digest) => digest,
+            None => return false,
+        };
+
+        let valid_kfrag_signature = self.proof.signature_for_proxy.verify(digest, signing_pk);
+
+        self.correct_commitment() & valid_kfrag_signature
+    }
+
+    /// Checks that the commitment is well-formed.
+    fn correct_commitment(&self) -> bool {
+        self.proof.commitment == &self.params.u * &self.key
+    }
+
+    /// Returns the digest the fragment's signature for the proxy is made for,
+    /// or `None` if some of the keys included in it were not provided.
+    fn digest_for_proxy(
+        &self,
+        delegating_pk: Option<&PublicKey>,
+        receiving_pk: Option<&PublicKey>,
+    ) -> Option<SignatureDigest> {
         if self.proof.delegating_key_signed && delegating_pk.is_none() {
-            return false;
+            return None;
         }
 
         if self.proof.receiving_key_signed && receiving_pk.is_none() {
-            return false;
+            return None;
         }
 
-        let u = self.params.u;
-
-        let kfrag_id = self.id;
-        let key = self.key;
-        let commitment = self.proof.commitment;
-        let precursor = self.precursor;
-
-        // We check that the commitment is well-formed
-        let correct_commitment = commitment == &u * &key;
-
-        let mut digest = SignatureDigest::new()
-            .chain_bytes(&kfrag_id)
-            .chain_point(&commitment)
-            .chain_point(&precursor)
+        let mut digest = SignatureDigest::new_with_mode(self.proof.hashing_mode)
+            .chain_bytes(&self.id)
+            .chain_point(&self.proof.commitment)
+            .chain_point(&self.precursor)
             .chain_bool(self.proof.delegating_key_signed)
-            .chain_bool(self.proof.receiving_key_signed);
+            .chain_bool(self.proof.receiving_key_signed)
+            .chain_u32(self.proof.threshold)
+            .chain_u32(self.proof.num_kfrags)
+            .chain_bytes(self.proof.validity.to_array());
         if self.proof.delegating_key_signed {
             // `delegating_pk` is guaranteed to be Some here.
             digest = digest.chain_pubkey(&delegating_pk.unwrap());
@@ -265,12 +604,139 @@ impl KeyFrag {
             // `receiving_pk` is guaranteed to be Some here.
             digest = digest.chain_pubkey(&receiving_pk.unwrap());
         }
-        let valid_kfrag_signature = digest.verify(&signing_pk, &self.proof.signature_for_proxy);
+        if self.proof.public_share_indices {
+            digest = digest.chain_bool(true);
+        }
+        Some(digest)
+    }
+
+    /// Verifies that the key fragment is consistent with the published commitment
+    /// to the share polynomial, signed with `signing_pk`.
+    ///
+    /// If all the fragments of a delegation pass this check,
+    /// they are shares of the same polynomial of the committed degree,
+    /// so the delegating party cannot blame the proxies for its own inconsistent fragments.
+    /// This does not replace [`verify()`](`Self::verify()`),
+    /// which checks the fragment's own signature.
+    ///
+    /// The check requires the share index to be public, so it always fails for the fragments
+    /// created without [`KeyFragOptions::public_share_indices()`].
+    pub fn verify_against_commitment(
+        &self,
+        commitment: &PolynomialCommitment,
+        signing_pk: &PublicKey,
+    ) -> bool {
+        let share_index = match self.public_share_index() {
+            Some(share_index) => share_index,
+            None => return false,
+        };
+
+        let u = self.params.u;
+
+        let valid_commitment = commitment.verify(signing_pk);
+        let same_delegation = commitment.precursor == self.precursor
+            && commitment.threshold() == self.threshold()
+            && commitment.num_kfrags() == self.num_kfrags();
+        let correct_kfrag_commitment = self.proof.commitment == &u * &self.key;
+
+        valid_commitment
+            && same_delegation
+            && correct_kfrag_commitment
+            && commitment.evaluate(&share_index) == self.proof.commitment
+    }
+
+    /// Returns the share index of the fragment if it was created with public share indices.
+    pub(crate) fn public_share_index(&self) -> Option<CurveScalar> {
+        if self.proof.public_share_indices {
+            Some(hash_to_public_polynomial_arg(&self.precursor, &self.id))
+        } else {
+            None
+        }
+    }
 
-        correct_commitment & valid_kfrag_signature
+    /// Returns the identifier of the key fragment.
+    pub fn id(&self) -> KeyFragID {
+        self.id
+    }
+
+    /// Returns the number of fragments necessary for decryption,
+    /// as signed by the delegating party.
+    pub fn threshold(&self) -> usize {
+        self.proof.threshold as usize
+    }
+
+    /// Returns the total number of fragments created along with this one,
+    /// as signed by the delegating party.
+    pub fn num_kfrags(&self) -> usize {
+        self.proof.num_kfrags as usize
+    }
+
+    /// Returns the period the fragment can be used for reencryption in,
+    /// as signed by the delegating party.
+    pub fn validity(&self) -> ValidityPeriod {
+        self.proof.validity
+    }
+
+    /// Returns the hashing mode of the fragment's signatures
+    /// and of the proofs of the capsule fragments created with it.
+    pub fn hashing_mode(&self) -> HashingMode {
+        self.proof.hashing_mode
+    }
+
+    /// Returns the scheme the fragment was signed with.
+    pub fn signature_scheme(&self) -> SignatureScheme {
+        self.proof.signature_for_proxy.scheme()
+    }
+
+    /// Returns `true` if the fragment was created with public share indices
+    /// (see [`KeyFragOptions::public_share_indices()`]).
+    pub fn public_share_indices(&self) -> bool {
+        self.proof.public_share_indices
     }
 }
 
+/// Verifies several key fragments signed with the same key
+/// (see [`KeyFrag::verify()`] for the meaning of the parameters).
+///
+/// The signatures made with [`SignatureScheme::Schnorr`] are verified in a batch,
+/// which is faster than verifying the fragments one by one.
+/// Returns `true` if all the fragments are valid.
+pub fn verify_kfrags(
+    kfrags: &[KeyFrag],
+    signing_pk: &PublicKey,
+    delegating_pk: Option<&PublicKey>,
+    receiving_pk: Option<&PublicKey>,
+) -> bool {
+    let mut schnorr_signatures = Vec::<(&SchnorrSignature, _)>::new();
+    for kfrag in kfrags {
+        let digest = match kfrag.digest_for_proxy(delegating_pk, receiving_pk) {
+            Some(digest) => digest,
+            None => return false,
+        };
+
+        if !kfrag.correct_commitment() {
+            return false;
+        }
+
+        match &kfrag.proof.signature_for_proxy {
+            KeyFragSignature::Schnorr(signature) => {
+                schnorr_signatures.push((signature, digest.schnorr_message()))
+            }
+            signature => {
+                if !signature.verify(digest, signing_pk) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    let batch: Vec<_> = schnorr_signatures
+        .iter()
+        .map(|(signature, message)| (*signature, signing_pk, message))
+        .collect();
+    SchnorrSignature::verify_batch(&batch)
+}
+
 struct KeyFragFactory {
     signing_sk: SecretKey,
     precursor: CurvePoint,
@@ -280,19 +746,56 @@ struct KeyFragFactory {
     delegating_pk: PublicKey,
     receiving_pk: PublicKey,
     coefficients: Box<[CurveScalar]>,
+    validity: ValidityPeriod,
+    hashing_mode: HashingMode,
+    signature_scheme: SignatureScheme,
+    public_share_indices: bool,
 }
 
 impl KeyFragFactory {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         params: &Parameters,
         delegating_sk: &SecretKey,
         receiving_pk: &PublicKey,
         signing_sk: &SecretKey,
         threshold: usize,
+        validity: &ValidityPeriod,
+        hashing_mode: HashingMode,
+        signature_scheme: SignatureScheme,
+        public_share_indices: bool,
     ) -> Self {
-        let g = CurvePoint::generator();
+        Self::from_delegating_scalar(
+            params,
+            &delegating_sk.to_secret_scalar(),
+            &PublicKey::from_secret_key(delegating_sk),
+            receiving_pk,
+            signing_sk,
+            threshold,
+            validity,
+            hashing_mode,
+            signature_scheme,
+            public_share_indices,
+        )
+    }
 
-        let delegating_pk = PublicKey::from_secret_key(delegating_sk);
+    /// `delegating_scalar` is the secret the reencrypted capsule points are multiplied by
+    /// (the delegating secret key for the first hop, or the re-delegating party's `d`
+    /// for the following ones), and `delegating_pk` is the key included in the signatures.
+    #[allow(clippy::too_many_arguments)]
+    fn from_delegating_scalar(
+        params: &Parameters,
+        delegating_scalar: &CurveScalar,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        signing_sk: &SecretKey,
+        threshold: usize,
+        validity: &ValidityPeriod,
+        hashing_mode: HashingMode,
+        signature_scheme: SignatureScheme,
+        public_share_indices: bool,
+    ) -> Self {
+        let g = CurvePoint::generator();
 
         let bob_pubkey_point = receiving_pk.to_point();
 
@@ -316,7 +819,7 @@ impl KeyFragFactory {
 
         // Coefficients of the generating polynomial
         // `invert()` is guaranteed not to panic because `d` is nonzero.
-        let coefficient0 = &delegating_sk.to_secret_scalar() * &(d.invert().unwrap());
+        let coefficient0 = delegating_scalar * &(d.invert().unwrap());
 
         let mut coefficients = Vec::<CurveScalar>::with_capacity(threshold);
         coefficients.push(coefficient0);
@@ -330,15 +833,130 @@ impl KeyFragFactory {
             bob_pubkey_point,
             dh_point,
             params: *params,
-            delegating_pk,
+            delegating_pk: *delegating_pk,
             receiving_pk: *receiving_pk,
             coefficients: coefficients.into_boxed_slice(),
+            validity: *validity,
+            hashing_mode,
+            signature_scheme,
+            public_share_indices,
         }
     }
+
+    /// Creates a factory for the update polynomial of the delegation
+    /// the given refresh key was produced for.
+    fn for_refresh(
+        refresh_key: &KeyFragRefreshKey,
+        signing_sk: &SecretKey,
+        threshold: usize,
+        validity: &ValidityPeriod,
+        hashing_mode: HashingMode,
+        signature_scheme: SignatureScheme,
+        public_share_indices: bool,
+    ) -> Self {
+        // The update polynomial has a zero constant term,
+        // so that the re-encryption key it shares stays the same.
+        let mut coefficients = Vec::<CurveScalar>::with_capacity(threshold);
+        coefficients.push(CurveScalar::default());
+        for _i in 1..threshold {
+            coefficients.push(CurveScalar::random_nonzero());
+        }
+
+        Self {
+            signing_sk: signing_sk.clone(),
+            precursor: refresh_key.precursor,
+            bob_pubkey_point: refresh_key.receiving_pk.to_point(),
+            dh_point: refresh_key.dh_point,
+            params: refresh_key.params,
+            delegating_pk: refresh_key.delegating_pk,
+            receiving_pk: refresh_key.receiving_pk,
+            coefficients: coefficients.into_boxed_slice(),
+            validity: *validity,
+            hashing_mode,
+            signature_scheme,
+            public_share_indices,
+        }
+    }
+
+    // The index of the re-encryption key share (which in Shamir's Secret
+    // Sharing corresponds to x in the tuple (x, f(x)), with f being the
+    // generating polynomial), is used to prevent reconstruction of the
+    // re-encryption key without Bob's intervention,
+    // unless the delegating party opted into public share indices.
+    fn share_index(&self, kfrag_id: &KeyFragID) -> CurveScalar {
+        if self.public_share_indices {
+            hash_to_public_polynomial_arg(&self.precursor, kfrag_id)
+        } else {
+            hash_to_polynomial_arg(
+                &self.precursor,
+                &self.bob_pubkey_point,
+                &self.dh_point,
+                kfrag_id,
+            )
+        }
+    }
+
+    fn refresh_key(&self) -> KeyFragRefreshKey {
+        KeyFragRefreshKey {
+            params: self.params,
+            delegating_pk: self.delegating_pk,
+            receiving_pk: self.receiving_pk,
+            precursor: self.precursor,
+            dh_point: self.dh_point,
+        }
+    }
+}
+
+/// Secret data of a delegation allowing the delegating party to refresh
+/// the key fragments with [`refresh_kfrags()`].
+///
+/// It must be kept private. It contains the precursor and the Diffie-Hellman point
+/// of the delegation, from which the secret `d` shared with the receiving party is derived,
+/// so whoever holds it learns `d`, and with it the share indices of the key fragments.
+/// Combined with a threshold of key fragments, `d` reveals the delegating secret key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyFragRefreshKey {
+    params: Parameters,
+    delegating_pk: PublicKey,
+    receiving_pk: PublicKey,
+    precursor: CurvePoint,
+    dh_point: CurvePoint,
+}
+
+type PublicKeySize = <PublicKey as SerializableToArray>::Size;
+type KeyFragRefreshKeySize =
+    op!(ParametersSize + PublicKeySize + PublicKeySize + PointSize + PointSize);
+
+impl SerializableToArray for KeyFragRefreshKey {
+    type Size = KeyFragRefreshKeySize;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.params
+            .to_array()
+            .concat(self.delegating_pk.to_array())
+            .concat(self.receiving_pk.to_array())
+            .concat(self.precursor.to_array())
+            .concat(self.dh_point.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (params, rest) = Parameters::take(*arr)?;
+        let (delegating_pk, rest) = PublicKey::take(rest)?;
+        let (receiving_pk, rest) = PublicKey::take(rest)?;
+        let (precursor, rest) = CurvePoint::take(rest)?;
+        let dh_point = CurvePoint::take_last(rest)?;
+        Some(Self {
+            params,
+            delegating_pk,
+            receiving_pk,
+            precursor,
+            dh_point,
+        })
+    }
 }
 
 // Coefficients of the generating polynomial
-fn poly_eval(coeffs: &[CurveScalar], x: &CurveScalar) -> CurveScalar {
+pub(crate) fn poly_eval(coeffs: &[CurveScalar], x: &CurveScalar) -> CurveScalar {
     let mut result: CurveScalar = coeffs[coeffs.len() - 1];
     for i in (0..coeffs.len() - 1).rev() {
         result = &(&result * x) + &coeffs[i];
@@ -352,6 +970,8 @@ fn poly_eval(coeffs: &[CurveScalar], x: &CurveScalar) -> CurveScalar {
 ///
 /// `threshold` sets the number of fragments necessary for decryption
 /// (that is, fragments created with `threshold > num_frags` will be useless).
+/// Both `threshold` and `num_kfrags` are signed and embedded in the resulting fragments,
+/// so that the receiving party can learn how many capsule fragments it needs to collect.
 ///
 /// `signing_sk` is used to sign the resulting [`KeyFrag`] and
 /// reencrypted [`CapsuleFrag`](`crate::CapsuleFrag`) objects, which can be later verified
@@ -374,11 +994,265 @@ pub fn generate_kfrags(
     sign_delegating_key: bool,
     sign_receiving_key: bool,
 ) -> Box<[KeyFrag]> {
-    let base = KeyFragFactory::new(params, delegating_sk, receiving_pk, signing_sk, threshold);
+    let (kfrags, _refresh_key) = generate_kfrags_with_options(
+        params,
+        delegating_sk,
+        receiving_pk,
+        signing_sk,
+        threshold,
+        num_kfrags,
+        sign_delegating_key,
+        sign_receiving_key,
+        &KeyFragOptions::new(),
+    );
+    kfrags
+}
+
+/// Optional properties of the key fragments created by [`generate_kfrags_with_options()`].
+///
+/// The default options produce the same fragments as [`generate_kfrags()`].
+/// The setters can be chained, and any combination of the options is allowed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyFragOptions {
+    validity: ValidityPeriod,
+    hashing_mode: HashingMode,
+    signature_scheme: SignatureScheme,
+    public_share_indices: bool,
+}
+
+impl KeyFragOptions {
+    /// Creates the default options: an unbounded validity period,
+    /// [`HashingMode::Sha256`], [`SignatureScheme::Ecdsa`], and private share indices.
+    pub fn new() -> Self {
+        Self {
+            validity: ValidityPeriod::unbounded(),
+            hashing_mode: HashingMode::Sha256,
+            signature_scheme: SignatureScheme::Ecdsa,
+            public_share_indices: false,
+        }
+    }
+
+    /// Restricts the resulting fragments to be used for reencryption within the given period
+    /// (see [`reencrypt_at()`](`crate::reencrypt_at()`)).
+    ///
+    /// The period is signed along with the fragments, so it can also be checked by the receiving
+    /// party via [`CapsuleFrag::validity()`](`crate::CapsuleFrag::validity()`).
+    pub fn validity(self, validity: &ValidityPeriod) -> Self {
+        Self {
+            validity: *validity,
+            ..self
+        }
+    }
+
+    /// Sets the hashing mode used in the signatures of the resulting fragments,
+    /// and in the proofs of the capsule fragments created with them.
+    ///
+    /// With [`HashingMode::Keccak256`] the key fragment signature included in a
+    /// [`CapsuleFrag`](`crate::CapsuleFrag`) and its proof of correct reencryption
+    /// can be checked by an EVM contract.
+    pub fn hashing_mode(self, hashing_mode: HashingMode) -> Self {
+        Self {
+            hashing_mode,
+            ..self
+        }
+    }
+
+    /// Sets the signature scheme the resulting fragments are signed with.
+    ///
+    /// [`SignatureScheme::Schnorr`] signatures are deterministic,
+    /// and can be verified in batches with [`verify_kfrags()`].
+    pub fn signature_scheme(self, signature_scheme: SignatureScheme) -> Self {
+        Self {
+            signature_scheme,
+            ..self
+        }
+    }
+
+    /// Derives the share indices of the resulting fragments from public data
+    /// (the fragment ID and the precursor), instead of the secret shared
+    /// with the receiving party.
+    ///
+    /// This is required to check the fragments against a
+    /// [`PolynomialCommitment`](`crate::PolynomialCommitment`),
+    /// but it weakens the collusion resistance of the delegation:
+    /// any `threshold` proxies can combine their fragments into the re-encryption key
+    /// `sk_a / d` without the receiving party.
+    /// It does not let them decrypt anything on their own,
+    /// but the receiving party colluding with them (or anyone who learns `d`)
+    /// learns the delegating secret key, while with the private share indices
+    /// the receiving party has to obtain the fragments themselves.
+    pub fn public_share_indices(self, public_share_indices: bool) -> Self {
+        Self {
+            public_share_indices,
+            ..self
+        }
+    }
+}
+
+impl Default for KeyFragOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Same as [`generate_kfrags()`], but creates the fragments with the given `options`,
+/// and also returns the data necessary to refresh them later with [`refresh_kfrags()`].
+#[allow(clippy::too_many_arguments)]
+pub fn generate_kfrags_with_options(
+    params: &Parameters,
+    delegating_sk: &SecretKey,
+    receiving_pk: &PublicKey,
+    signing_sk: &SecretKey,
+    threshold: usize,
+    num_kfrags: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+    options: &KeyFragOptions,
+) -> (Box<[KeyFrag]>, KeyFragRefreshKey) {
+    let base = KeyFragFactory::new(
+        params,
+        delegating_sk,
+        receiving_pk,
+        signing_sk,
+        threshold,
+        &options.validity,
+        options.hashing_mode,
+        options.signature_scheme,
+        options.public_share_indices,
+    );
 
     let mut result = Vec::<KeyFrag>::new();
     for _ in 0..num_kfrags {
-        result.push(KeyFrag::new(&base, sign_delegating_key, sign_receiving_key));
+        result.push(KeyFrag::new(
+            &base,
+            num_kfrags as u32,
+            sign_delegating_key,
+            sign_receiving_key,
+        ));
+    }
+
+    (result.into_boxed_slice(), base.refresh_key())
+}
+
+/// Refreshes the key fragments of a delegation, keeping their IDs,
+/// the threshold and the total number of fragments the same.
+///
+/// The new fragments produce the same result as the old ones,
+/// but a mix of old and new fragments does not,
+/// so the fragments leaked before the refresh become useless
+/// once the proxies replace their fragments with the new ones.
+///
+/// Each call uses a fresh update polynomial, so all the fragments of a delegation
+/// must be passed to a single call, and every proxy must switch to its new fragment,
+/// discarding the old one.
+/// Fragments refreshed in different calls, or a mix of refreshed and old fragments,
+/// still pass verification individually, but the receiving party cannot combine
+/// the resulting capsule fragments: [`decrypt_reencrypted()`](`crate::decrypt_reencrypted()`)
+/// fails capsule validation with
+/// [`OpenReencryptedError::ValidationFailed`](`crate::OpenReencryptedError::ValidationFailed`).
+///
+/// `refresh_key` gives its holder the delegation secret `d`
+/// (see [`KeyFragRefreshKey`]), so it must never be passed to the proxies.
+///
+/// Since the fragments are signed, the refresh has to be performed
+/// by the owner of `signing_sk` (the same key that was used to create them).
+///
+/// Returns `None` if `kfrags` is empty, or some of them were not created
+/// along with `refresh_key` (see [`generate_kfrags_with_options()`]).
+pub fn refresh_kfrags(
+    refresh_key: &KeyFragRefreshKey,
+    signing_sk: &SecretKey,
+    kfrags: &[KeyFrag],
+) -> Option<Box<[KeyFrag]>> {
+    let first = kfrags.first()?;
+    let threshold = first.threshold();
+    let validity = first.validity();
+    let hashing_mode = first.hashing_mode();
+    let signature_scheme = first.signature_scheme();
+    let public_share_indices = first.public_share_indices();
+    if !kfrags.iter().all(|kfrag| {
+        kfrag.precursor == refresh_key.precursor
+            && kfrag.params == refresh_key.params
+            && kfrag.threshold() == threshold
+            && kfrag.validity() == validity
+            && kfrag.hashing_mode() == hashing_mode
+            && kfrag.signature_scheme() == signature_scheme
+            && kfrag.public_share_indices() == public_share_indices
+    }) {
+        return None;
+    }
+
+    let update = KeyFragFactory::for_refresh(
+        refresh_key,
+        signing_sk,
+        threshold,
+        &validity,
+        hashing_mode,
+        signature_scheme,
+        public_share_indices,
+    );
+    let result: Vec<KeyFrag> = kfrags
+        .iter()
+        .map(|kfrag| kfrag.refreshed(&update))
+        .collect();
+    Some(result.into_boxed_slice())
+}
+
+/// Creates `num_kfrags` fragments allowing the creator of `receiving_pk` to decrypt
+/// the ciphertexts that were reencrypted for the owner of `delegating_sk`
+/// (the receiving party of the original delegation) and combined into `capsule`.
+///
+/// The fragments are bound to the precursor of the first-level capsule fragments,
+/// so they can be used with any [`ReencryptedCapsule`] produced
+/// from the same original [`generate_kfrags()`] call.
+/// The resulting fragments are used with
+/// [`reencrypt_redelegated()`](`crate::reencrypt_redelegated()`),
+/// and verified with [`KeyFrag::verify()`] using the public key of `delegating_sk`
+/// as the delegating key.
+///
+/// The resulting fragments have an unbounded validity period and private share indices,
+/// use [`HashingMode::Sha256`], and are signed with [`SignatureScheme::Ecdsa`].
+///
+/// The rest of the parameters are the same as in [`generate_kfrags()`].
+#[allow(clippy::too_many_arguments)]
+pub fn generate_redelegation_kfrags(
+    delegating_sk: &SecretKey,
+    capsule: &ReencryptedCapsule,
+    receiving_pk: &PublicKey,
+    signing_sk: &SecretKey,
+    threshold: usize,
+    num_kfrags: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Box<[KeyFrag]> {
+    let delegating_pk = PublicKey::from_secret_key(delegating_sk);
+    let precursor = capsule.precursor;
+    let dh_point = &precursor * &delegating_sk.to_secret_scalar();
+
+    // The same secret value the delegating party uses to open the reencrypted capsule.
+    let d = hash_to_shared_secret(&precursor, &delegating_pk.to_point(), &dh_point);
+
+    let base = KeyFragFactory::from_delegating_scalar(
+        &capsule.capsule.params,
+        &d,
+        &delegating_pk,
+        receiving_pk,
+        signing_sk,
+        threshold,
+        &ValidityPeriod::unbounded(),
+        HashingMode::Sha256,
+        SignatureScheme::Ecdsa,
+        false,
+    );
+
+    let mut result = Vec::<KeyFrag>::new();
+    for _ in 0..num_kfrags {
+        result.push(KeyFrag::new(
+            &base,
+            num_kfrags as u32,
+            sign_delegating_key,
+            sign_receiving_key,
+        ));
     }
 
     result.into_boxed_slice()
@@ -388,9 +1262,21 @@ pub fn generate_kfrags(
 mod tests {
 
     use alloc::boxed::Box;
-
-    use super::{generate_kfrags, KeyFrag};
-    use crate::{Parameters, PublicKey, SecretKey, SerializableToArray};
+    use alloc::vec::Vec;
+
+    use super::{
+        generate_kfrags, generate_kfrags_with_options, refresh_kfrags, verify_kfrags, KeyFrag,
+        KeyFragOptions, KeyFragSignature, SignatureScheme,
+    };
+    use crate::capsule::lambda_coeff;
+    use crate::curve::CurveScalar;
+    use crate::hashing::HashingMode;
+    use crate::hashing_ds::{hash_to_public_polynomial_arg, hash_to_shared_secret};
+    use crate::{
+        decrypt_reencrypted, encrypt, reencrypt, CapsuleFrag, KeyFragRefreshKey,
+        OpenReencryptedError, Parameters, PublicKey, ReencryptionError, SecretKey,
+        SerializableToArray,
+    };
 
     fn prepare_kfrags(
         sign_delegating_key: bool,
@@ -450,4 +1336,354 @@ mod tests {
         assert!(kfrags[0].verify(&signing_pk, None, None));
         assert!(!kfrags[0].verify(&delegating_pk, None, None));
     }
+
+    #[test]
+    fn test_recoverable_signatures() {
+        let params = Parameters::new();
+        let delegating_sk = SecretKey::random();
+        let signing_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&SecretKey::random());
+
+        let (kfrags, _refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+            &KeyFragOptions::new(),
+        );
+        let (keccak_kfrags, _refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+            &KeyFragOptions::new().hashing_mode(HashingMode::Keccak256),
+        );
+
+        // Only the Keccak mode uses recoverable signatures
+        assert!(matches!(
+            kfrags[0].proof.signature_for_proxy,
+            KeyFragSignature::Ecdsa(_)
+        ));
+        assert!(matches!(
+            keccak_kfrags[0].proof.signature_for_proxy,
+            KeyFragSignature::RecoverableEcdsa(_)
+        ));
+
+        // A signature of the wrong kind for the mode is rejected on deserialization
+        let mut mismatched = kfrags[0].clone();
+        mismatched.proof.signature_for_proxy = keccak_kfrags[0].proof.signature_for_proxy.clone();
+        assert!(KeyFrag::from_array(&mismatched.to_array()).is_none());
+    }
+
+    #[test]
+    fn test_schnorr_signatures() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let (kfrags, _refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+            &KeyFragOptions::new().signature_scheme(SignatureScheme::Schnorr),
+        );
+
+        for kfrag in kfrags.iter() {
+            assert_eq!(kfrag.signature_scheme(), SignatureScheme::Schnorr);
+            assert!(kfrag.verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk)));
+
+            // The scheme is recorded in the serialized form
+            let kfrag_back = KeyFrag::from_array(&kfrag.to_array()).unwrap();
+            assert_eq!(kfrag, &kfrag_back);
+        }
+
+        assert!(verify_kfrags(
+            &kfrags,
+            &signing_pk,
+            Some(&delegating_pk),
+            Some(&receiving_pk)
+        ));
+        assert!(!verify_kfrags(
+            &kfrags,
+            &delegating_pk,
+            Some(&delegating_pk),
+            Some(&receiving_pk)
+        ));
+        assert!(!verify_kfrags(
+            &kfrags,
+            &signing_pk,
+            None,
+            Some(&receiving_pk)
+        ));
+
+        // A fragment with a substituted key share fails the batch verification
+        let mut bad_kfrags = kfrags.to_vec();
+        bad_kfrags[1].key = kfrags[0].key;
+        assert!(!verify_kfrags(
+            &bad_kfrags,
+            &signing_pk,
+            Some(&delegating_pk),
+            Some(&receiving_pk)
+        ));
+
+        // ECDSA-signed fragments in the set are verified individually
+        let (_, _, _, ecdsa_kfrags) = prepare_kfrags(true, true);
+        let mut mixed_kfrags = kfrags.to_vec();
+        mixed_kfrags.push(ecdsa_kfrags[0].clone());
+        assert!(!verify_kfrags(
+            &mixed_kfrags,
+            &signing_pk,
+            Some(&delegating_pk),
+            Some(&receiving_pk)
+        ));
+
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, &kfrag, None))
+            .collect();
+        for cfrag in cfrags.iter() {
+            assert_eq!(cfrag.signature_scheme(), SignatureScheme::Schnorr);
+            assert!(cfrag.verify(&capsule, &delegating_pk, &receiving_pk, &signing_pk));
+        }
+
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[test]
+    fn test_refresh() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let (kfrags, refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+            &KeyFragOptions::new(),
+        );
+
+        let refresh_key_arr = refresh_key.to_array();
+        let refresh_key_back = KeyFragRefreshKey::from_array(&refresh_key_arr).unwrap();
+        assert_eq!(refresh_key, refresh_key_back);
+
+        let new_kfrags = refresh_kfrags(&refresh_key, &signing_sk, &kfrags).unwrap();
+        for (kfrag, new_kfrag) in kfrags.iter().zip(new_kfrags.iter()) {
+            assert_eq!(kfrag.id(), new_kfrag.id());
+            assert_eq!(new_kfrag.threshold(), 2);
+            assert_eq!(new_kfrag.num_kfrags(), 3);
+            assert_ne!(kfrag.key, new_kfrag.key);
+            assert!(new_kfrag.verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk)));
+        }
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let new_cfrags: Vec<CapsuleFrag> = new_kfrags[1..3]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+        assert!(new_cfrags.iter().all(|cfrag| cfrag.verify(
+            &capsule,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk
+        )));
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &new_cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        // An old fragment mixed with a new one
+        let mixed_cfrags = [
+            reencrypt(&capsule, &kfrags[0], None),
+            reencrypt(&capsule, &new_kfrags[1], None),
+        ];
+        assert_eq!(
+            decrypt_reencrypted(
+                &receiving_sk,
+                &delegating_pk,
+                &capsule,
+                &mixed_cfrags,
+                &ciphertext
+            ),
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+
+        // Fragments from a different delegation
+        let other_kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        assert!(refresh_kfrags(&refresh_key, &signing_sk, &other_kfrags).is_none());
+        assert!(refresh_kfrags(&refresh_key, &signing_sk, &[]).is_none());
+    }
+
+    #[test]
+    fn test_share_indices() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        // Interpolates the re-encryption key `sk_a / d` at zero,
+        // using only the share indices anyone can compute from the fragments.
+        let collude = |kfrags: &[KeyFrag]| {
+            let xs: Vec<CurveScalar> = kfrags
+                .iter()
+                .map(|kfrag| hash_to_public_polynomial_arg(&kfrag.precursor, &kfrag.id))
+                .collect();
+            let mut result = CurveScalar::default();
+            for (i, kfrag) in kfrags.iter().enumerate() {
+                result = &result + &(&kfrag.key * &lambda_coeff(&xs, i).unwrap());
+            }
+            result
+        };
+
+        let reencryption_key = |kfrag: &KeyFrag| {
+            let dh_point = &kfrag.precursor * &receiving_sk.to_secret_scalar();
+            let d = hash_to_shared_secret(&kfrag.precursor, &receiving_pk.to_point(), &dh_point);
+            &delegating_sk.to_secret_scalar() * &d.invert().unwrap()
+        };
+
+        // By default the share indices depend on the receiving party's secret,
+        // so a threshold of proxies cannot reconstruct the re-encryption key on their own.
+        let private_kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        assert!(!private_kfrags[0].public_share_indices());
+        assert!(private_kfrags[0].public_share_index().is_none());
+        assert_ne!(
+            collude(&private_kfrags[0..2]),
+            reencryption_key(&private_kfrags[0])
+        );
+
+        // With public share indices they can (which is the documented trade-off).
+        let (public_kfrags, _refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+            &KeyFragOptions::new().public_share_indices(true),
+        );
+        assert!(public_kfrags[0].public_share_indices());
+        assert_eq!(
+            collude(&public_kfrags[0..2]),
+            reencryption_key(&public_kfrags[0])
+        );
+
+        // The flag is signed, so it cannot be flipped by the proxy.
+        let mut flipped = public_kfrags[0].clone();
+        flipped.proof.public_share_indices = false;
+        assert!(!flipped.verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk)));
+
+        // Public share index fragments still decrypt normally.
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+        let cfrags: Vec<CapsuleFrag> = public_kfrags[1..3]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+        assert!(cfrags.iter().all(|cfrag| cfrag.verify(
+            &capsule,
+            &delegating_pk,
+            &receiving_pk,
+            &signing_pk
+        )));
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        // Fragments with private and public share indices cannot be combined.
+        let mixed_cfrags = [
+            reencrypt(&capsule, &private_kfrags[0], None),
+            reencrypt(&capsule, &public_kfrags[1], None),
+        ];
+        assert!(decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &mixed_cfrags,
+            &ciphertext
+        )
+        .is_err());
+    }
 }
@@ -96,21 +96,63 @@ extern crate typenum;
 pub mod bench; // Re-export some internals for benchmarks.
 mod capsule;
 mod capsule_frag;
+mod cfrag_collector;
+mod commitment;
 mod curve;
 mod dem;
+mod distributed_kfrags;
+mod dkg;
+mod evidence;
 mod hashing;
 mod hashing_ds;
+mod hybrid;
+mod key_factory;
+mod key_file;
 mod key_frag;
 mod params;
 mod pre;
+mod reencrypted_capsule;
+mod revocation;
+mod schnorr;
+mod threshold_decryption;
 mod traits;
 
-pub use key_frag::generate_kfrags;
-pub use pre::{decrypt_original, decrypt_reencrypted, encrypt, reencrypt};
+pub use key_frag::{
+    generate_kfrags, generate_kfrags_with_options, generate_redelegation_kfrags, refresh_kfrags,
+    verify_kfrags,
+};
+pub use pre::{
+    decrypt_original, decrypt_original_hybrid, decrypt_original_signed, decrypt_original_threshold,
+    decrypt_reencrypted, decrypt_reencrypted_hybrid, decrypt_reencrypted_robust,
+    decrypt_reencrypted_signed, decrypt_reencrypted_threshold, encrypt, encrypt_deterministic,
+    encrypt_hybrid, encrypt_signed, reencrypt, reencrypt_at, reencrypt_redelegated,
+    reencrypt_signed, ReencryptionError,
+};
 
-pub use capsule::Capsule;
-pub use capsule_frag::CapsuleFrag;
+pub use capsule::{Capsule, OpenReencryptedError};
+pub use capsule_frag::{CapsuleFrag, SignedCapsuleFrag};
+pub use cfrag_collector::{CfragCollector, CollectCfragError};
+pub use commitment::PolynomialCommitment;
 pub use curve::{PublicKey, SecretKey};
-pub use key_frag::KeyFrag;
+pub use distributed_kfrags::{
+    ContributionCommitment, DistributedKeyFragError, DistributedKeyFragSetup, PartialKeyFrag,
+    UnkeyedKeyFrag,
+};
+pub use dkg::{DkgCommitment, DkgComplaint, DkgError, DkgOutput, DkgParticipant, DkgShare};
+pub use evidence::{verify_evidence, IncorrectCfragEvidence};
+pub use hashing::HashingMode;
+pub use hybrid::{
+    HybridCapsule, PostQuantumDecapsulator, PostQuantumEncapsulator, PqEncapsulation,
+};
+pub use key_factory::SecretKeyFactory;
+pub use key_file::{KeyFileError, KeyFileLimits, SecretKeyType};
+pub use key_frag::{
+    KeyFrag, KeyFragID, KeyFragOptions, KeyFragRefreshKey, SignatureScheme, ValidityPeriod,
+};
 pub use params::Parameters;
+pub use reencrypted_capsule::ReencryptedCapsule;
+pub use revocation::{Revocation, RevocationSet};
+pub use threshold_decryption::{
+    split_secret_key, DecryptionShare, PublicKeyShare, SecretKeyShare, ThresholdDecryptionError,
+};
 pub use traits::SerializableToArray;
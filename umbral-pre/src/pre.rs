@@ -1,14 +1,60 @@
 //! The high-level functional reencryption API.
 
-use crate::capsule::Capsule;
-use crate::capsule_frag::CapsuleFrag;
-use crate::curve::{PublicKey, SecretKey};
-use crate::dem::UmbralDEM;
-use crate::key_frag::KeyFrag;
+use crate::capsule::{Capsule, OpenReencryptedError};
+use crate::capsule_frag::{CapsuleFrag, SignedCapsuleFrag};
+use crate::curve::{PublicKey, SecretKey, Signature};
+use crate::dem::{kdf, NonceSize, UmbralDEM};
+use crate::hashing::{BytesDigest, ScalarDigest, SignatureDigest};
+use crate::hybrid::{HybridCapsule, PostQuantumDecapsulator, PostQuantumEncapsulator};
+use crate::key_frag::{KeyFrag, KeyFragID};
 use crate::params::Parameters;
+use crate::reencrypted_capsule::ReencryptedCapsule;
+use crate::threshold_decryption::{
+    combine_decryption_shares, DecryptionShare, ThresholdDecryptionError,
+};
 use crate::traits::SerializableToArray;
 
 use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use generic_array::GenericArray;
+use typenum::Unsigned;
+
+/// Errors that can happen when decrypting a reencrypted ciphertext.
+#[derive(Debug, PartialEq)]
+pub enum ReencryptionError {
+    /// An error when opening a capsule. See [`OpenReencryptedError`] for the options.
+    OnOpen(OpenReencryptedError),
+    /// An error when decrypting the ciphertext with the key recovered from the capsule.
+    OnDecryption,
+    /// An error when combining the shares of the receiving key.
+    /// See [`ThresholdDecryptionError`] for the options.
+    OnShareCombination(ThresholdDecryptionError),
+    /// The ciphertext decrypted successfully, but does not carry a valid sender signature
+    /// (see [`encrypt_signed()`]).
+    SenderVerificationFailed,
+    /// An error when decapsulating the post-quantum part of a [`HybridCapsule`].
+    OnPostQuantumDecapsulation,
+}
+
+impl fmt::Display for ReencryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OnOpen(err) => write!(f, "Re-encryption error on open: {}", err),
+            Self::OnDecryption => write!(f, "Re-encryption error on decryption"),
+            Self::OnShareCombination(err) => {
+                write!(f, "Re-encryption error on share combination: {}", err)
+            }
+            Self::SenderVerificationFailed => {
+                write!(f, "Re-encryption error on sender verification")
+            }
+            Self::OnPostQuantumDecapsulation => {
+                write!(f, "Re-encryption error on post-quantum decapsulation")
+            }
+        }
+    }
+}
 
 /// Encrypts the given plaintext message using a DEM scheme,
 /// and encapsulates the key for later reencryption.
@@ -25,6 +71,55 @@ pub fn encrypt(
     Some((capsule, ciphertext))
 }
 
+/// Encrypts the given plaintext message deterministically, so that the same plaintext
+/// encrypted with the same `pk` and `dedup_key` always produces the same
+/// [`Capsule`] and ciphertext (e.g. to allow the storage to deduplicate them).
+///
+/// The ephemeral scalars of the capsule and the DEM nonce are derived
+/// from a keyed hash (HKDF-SHA256 with `dedup_key` as the salt) of the plaintext.
+/// The result can be decrypted and reencrypted in the same way as the one from [`encrypt()`].
+///
+/// This mode leaks more than [`encrypt()`]:
+/// - anyone seeing the capsules or ciphertexts learns which of them
+///   contain identical plaintexts (for the same `pk` and `dedup_key`);
+/// - anyone knowing `dedup_key` (or everyone, if it is public or empty) can confirm
+///   a guess of the plaintext by encrypting it and comparing the results,
+///   which makes low-entropy plaintexts recoverable by a brute-force search.
+///
+/// Therefore `dedup_key` should be a secret shared only by the parties
+/// whose data is supposed to be deduplicated.
+/// Returns `None` under the same conditions as [`encrypt()`].
+pub fn encrypt_deterministic(
+    params: &Parameters,
+    pk: &PublicKey,
+    plaintext: &[u8],
+    dedup_key: &[u8],
+) -> Option<(Capsule, Box<[u8]>)> {
+    let seed = kdf(plaintext, Some(dedup_key), Some(&pk.to_array()));
+
+    let priv_r = ScalarDigest::new_with_dst(b"CONVERGENT_R")
+        .chain_bytes(seed)
+        .finalize();
+    let priv_u = ScalarDigest::new_with_dst(b"CONVERGENT_U")
+        .chain_bytes(seed)
+        .finalize();
+    // The scalars are zero with a negligible probability.
+    if priv_r.is_zero() || priv_u.is_zero() {
+        return None;
+    }
+
+    let nonce_digest = BytesDigest::new_with_dst(b"CONVERGENT_NONCE")
+        .chain_bytes(&seed)
+        .finalize();
+    let nonce = GenericArray::<u8, NonceSize>::from_slice(&nonce_digest[..NonceSize::to_usize()]);
+
+    let (capsule, key_seed) = Capsule::from_pubkey_and_scalars(params, pk, &priv_r, &priv_u);
+    let dem = UmbralDEM::new(&key_seed.to_array());
+    let capsule_bytes = capsule.to_array();
+    let ciphertext = dem.encrypt_with_nonce(plaintext, &capsule_bytes, nonce)?;
+    Some((capsule, ciphertext))
+}
+
 /// Attempts to decrypt the ciphertext using the original encryptor's
 /// secret key.
 pub fn decrypt_original(
@@ -37,6 +132,146 @@ pub fn decrypt_original(
     dem.decrypt(ciphertext, &capsule.to_array())
 }
 
+type PublicKeySize = <PublicKey as SerializableToArray>::Size;
+type SignatureSize = <Signature as SerializableToArray>::Size;
+
+fn signcryption_digest(
+    capsule: &Capsule,
+    recipient_pk: &PublicKey,
+    sender_pk: &PublicKey,
+    plaintext: &[u8],
+) -> SignatureDigest {
+    SignatureDigest::new()
+        .chain_bytes(b"SIGNCRYPTION")
+        .chain_bytes(capsule.to_array())
+        .chain_pubkey(recipient_pk)
+        .chain_pubkey(sender_pk)
+        .chain_bytes(plaintext)
+}
+
+/// Splits a payload created by [`encrypt_signed()`] into the plaintext and the sender key,
+/// checking the sender signature.
+fn open_signed_payload(
+    payload: &[u8],
+    capsule: &Capsule,
+    recipient_pk: &PublicKey,
+) -> Option<(Box<[u8]>, PublicKey)> {
+    let pk_size = PublicKeySize::to_usize();
+    let signature_size = SignatureSize::to_usize();
+    if payload.len() < pk_size + signature_size {
+        return None;
+    }
+    let sender_pk = PublicKey::from_bytes(&payload[..pk_size])?;
+    let signature = Signature::from_bytes(&payload[pk_size..pk_size + signature_size])?;
+    let plaintext = &payload[pk_size + signature_size..];
+    if !signcryption_digest(capsule, recipient_pk, &sender_pk, plaintext)
+        .verify(&sender_pk, &signature)
+    {
+        return None;
+    }
+    Some((plaintext.into(), sender_pk))
+}
+
+/// Encrypts the given plaintext message as [`encrypt()`] does,
+/// additionally signing it with the sender's `sender_sk`.
+///
+/// The signature covers the capsule, the recipient key and the plaintext,
+/// and is encrypted along with the latter, so only the parties able to decrypt
+/// the ciphertext learn who the sender is.
+/// Since reencryption does not change the capsule or the ciphertext,
+/// the signature can be checked by the delegatee as well,
+/// with [`decrypt_original_signed()`] or [`decrypt_reencrypted_signed()`].
+pub fn encrypt_signed(
+    params: &Parameters,
+    recipient_pk: &PublicKey,
+    sender_sk: &SecretKey,
+    plaintext: &[u8],
+) -> Option<(Capsule, Box<[u8]>)> {
+    let (capsule, key_seed) = Capsule::from_pubkey(params, recipient_pk);
+    let sender_pk = PublicKey::from_secret_key(sender_sk);
+    let signature =
+        signcryption_digest(&capsule, recipient_pk, &sender_pk, plaintext).sign(sender_sk);
+
+    let mut payload = Vec::<u8>::new();
+    payload.extend_from_slice(&sender_pk.to_array());
+    payload.extend_from_slice(&signature.to_array());
+    payload.extend_from_slice(plaintext);
+
+    let dem = UmbralDEM::new(&key_seed.to_array());
+    let capsule_bytes = capsule.to_array();
+    let ciphertext = dem.encrypt(&payload, &capsule_bytes)?;
+    Some((capsule, ciphertext))
+}
+
+/// Attempts to decrypt the ciphertext created by [`encrypt_signed()`]
+/// using the original encryptor's secret key.
+///
+/// Returns the plaintext along with the verified public key of the sender.
+pub fn decrypt_original_signed(
+    decrypting_sk: &SecretKey,
+    capsule: &Capsule,
+    ciphertext: impl AsRef<[u8]>,
+) -> Option<(Box<[u8]>, PublicKey)> {
+    let payload = decrypt_original(decrypting_sk, capsule, ciphertext)?;
+    let recipient_pk = PublicKey::from_secret_key(decrypting_sk);
+    open_signed_payload(&payload, capsule, &recipient_pk)
+}
+
+/// Attempts to decrypt the ciphertext using the partial openings of the capsule
+/// created by the holders of the shares of the original encryptor's secret key
+/// (see [`split_secret_key()`](`crate::split_secret_key()`)).
+///
+/// At least `threshold` distinct shares are necessary.
+/// One can call [`DecryptionShare::verify()`] before decryption to check their integrity.
+pub fn decrypt_original_threshold(
+    capsule: &Capsule,
+    shares: &[DecryptionShare],
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, ThresholdDecryptionError> {
+    let key_seed = combine_decryption_shares(shares)?;
+    let dem = UmbralDEM::new(&key_seed.to_array());
+    dem.decrypt(ciphertext, &capsule.to_array())
+        .ok_or(ThresholdDecryptionError::OnDecryption)
+}
+
+/// Encrypts the given plaintext message using a DEM scheme
+/// with the key encapsulated both in an Umbral [`Capsule`] for `pk`
+/// and with a post-quantum KEM for `pq_pk`.
+///
+/// This protects long-term ciphertexts from a future quantum adversary
+/// as long as the post-quantum KEM is secure.
+/// Note that only the Umbral part can be reencrypted by proxies,
+/// see [`HybridCapsule`] for details.
+pub fn encrypt_hybrid(
+    params: &Parameters,
+    pk: &PublicKey,
+    pq_pk: &impl PostQuantumEncapsulator,
+    plaintext: &[u8],
+) -> Option<(HybridCapsule, Box<[u8]>)> {
+    let (capsule, key_seed) = Capsule::from_pubkey(params, pk);
+    let pq_encapsulation = pq_pk.encapsulate()?;
+    let hybrid_capsule = HybridCapsule::new(capsule, &pq_encapsulation.ciphertext);
+    let dem = UmbralDEM::new(
+        &hybrid_capsule.combine_key_seeds(&key_seed, &pq_encapsulation.shared_secret),
+    );
+    let ciphertext = dem.encrypt(plaintext, &hybrid_capsule.to_bytes())?;
+    Some((hybrid_capsule, ciphertext))
+}
+
+/// Attempts to decrypt the ciphertext created by [`encrypt_hybrid()`]
+/// using the original encryptor's secret key and the post-quantum secret key.
+pub fn decrypt_original_hybrid(
+    decrypting_sk: &SecretKey,
+    pq_sk: &impl PostQuantumDecapsulator,
+    hybrid_capsule: &HybridCapsule,
+    ciphertext: impl AsRef<[u8]>,
+) -> Option<Box<[u8]>> {
+    let key_seed = hybrid_capsule.capsule().open_original(decrypting_sk);
+    let pq_secret = pq_sk.decapsulate(hybrid_capsule.pq_ciphertext())?;
+    let dem = UmbralDEM::new(&hybrid_capsule.combine_key_seeds(&key_seed, &pq_secret));
+    dem.decrypt(ciphertext, &hybrid_capsule.to_bytes())
+}
+
 /// Reencrypts a [`Capsule`] object with a key fragment, creating a capsule fragment.
 ///
 /// Having `threshold` (see [`generate_kfrags()`](`crate::generate_kfrags()`))
@@ -48,6 +283,53 @@ pub fn reencrypt(capsule: &Capsule, kfrag: &KeyFrag, metadata: Option<&[u8]>) ->
     CapsuleFrag::reencrypted(capsule, kfrag, metadata)
 }
 
+/// Reencrypts a [`Capsule`] object with a key fragment, as [`reencrypt()`] does,
+/// and signs the resulting capsule fragment (along with the hash of `metadata`)
+/// with the proxy's `proxy_sk`.
+///
+/// The receiving party can check the signature with [`SignedCapsuleFrag::verify()`]
+/// to hold the proxy accountable for the fragment.
+pub fn reencrypt_signed(
+    capsule: &Capsule,
+    kfrag: &KeyFrag,
+    metadata: Option<&[u8]>,
+    proxy_sk: &SecretKey,
+) -> SignedCapsuleFrag {
+    SignedCapsuleFrag::new(reencrypt(capsule, kfrag, metadata), proxy_sk)
+}
+
+/// Reencrypts a [`Capsule`] object with a key fragment, creating a capsule fragment,
+/// but only if `now` lies within the fragment's validity period
+/// (see [`KeyFragOptions::validity()`](`crate::KeyFragOptions::validity()`)).
+///
+/// `now` must be given in the same units the validity period was created with.
+/// Returns `None` if the key fragment cannot be used at the given time.
+pub fn reencrypt_at(
+    capsule: &Capsule,
+    kfrag: &KeyFrag,
+    metadata: Option<&[u8]>,
+    now: u64,
+) -> Option<CapsuleFrag> {
+    if !kfrag.validity().contains(now) {
+        return None;
+    }
+    Some(reencrypt(capsule, kfrag, metadata))
+}
+
+/// Reencrypts a [`ReencryptedCapsule`] object with a key fragment created by
+/// [`generate_redelegation_kfrags()`](`crate::generate_redelegation_kfrags()`),
+/// creating a capsule fragment for the next receiving party.
+///
+/// The fragments can be verified with [`CapsuleFrag::verify_redelegated()`],
+/// and decrypted with [`decrypt_reencrypted()`] along with the original capsule.
+pub fn reencrypt_redelegated(
+    capsule: &ReencryptedCapsule,
+    kfrag: &KeyFrag,
+    metadata: Option<&[u8]>,
+) -> CapsuleFrag {
+    CapsuleFrag::reencrypted_redelegated(capsule, kfrag, metadata)
+}
+
 /// Decrypts the ciphertext using previously reencrypted capsule fragments.
 ///
 /// `decrypting_sk` is the secret key whose associated public key was used in
@@ -57,30 +339,184 @@ pub fn reencrypt(capsule: &Capsule, kfrag: &KeyFrag, metadata: Option<&[u8]>) ->
 /// Used to check the validity of decryption.
 ///
 /// One can call [`CapsuleFrag::verify()`] before reencryption to check its integrity.
+///
+/// If fewer capsule fragments than the threshold they were created with are given,
+/// returns [`OpenReencryptedError::NotEnoughCapsuleFrags`] wrapped in
+/// [`ReencryptionError::OnOpen`], from which one can find out how many more are needed.
 pub fn decrypt_reencrypted(
     decrypting_sk: &SecretKey,
     delegating_pk: &PublicKey,
     capsule: &Capsule,
     cfrags: &[CapsuleFrag],
     ciphertext: impl AsRef<[u8]>,
-) -> Option<Box<[u8]>> {
-    let key_seed = capsule.open_reencrypted(decrypting_sk, delegating_pk, cfrags)?;
+) -> Result<Box<[u8]>, ReencryptionError> {
+    let key_seed = capsule
+        .open_reencrypted(decrypting_sk, delegating_pk, cfrags)
+        .map_err(ReencryptionError::OnOpen)?;
     let dem = UmbralDEM::new(&key_seed.to_array());
     dem.decrypt(&ciphertext, &capsule.to_array())
+        .ok_or(ReencryptionError::OnDecryption)
+}
+
+/// Decrypts the ciphertext created by [`encrypt_signed()`]
+/// using previously reencrypted capsule fragments.
+///
+/// Returns the plaintext along with the verified public key of the sender,
+/// or [`ReencryptionError::SenderVerificationFailed`] if the sender signature is invalid.
+///
+/// The parameters are the same as in [`decrypt_reencrypted()`].
+pub fn decrypt_reencrypted_signed(
+    decrypting_sk: &SecretKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    cfrags: &[CapsuleFrag],
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<(Box<[u8]>, PublicKey), ReencryptionError> {
+    let payload = decrypt_reencrypted(decrypting_sk, delegating_pk, capsule, cfrags, ciphertext)?;
+    open_signed_payload(&payload, capsule, delegating_pk)
+        .ok_or(ReencryptionError::SenderVerificationFailed)
+}
+
+/// Decrypts the ciphertext created by [`encrypt_hybrid()`]
+/// using the capsule fragments reencrypted from [`HybridCapsule::capsule()`].
+///
+/// `pq_sk` must be able to decapsulate the post-quantum part of the capsule;
+/// since it is not affected by reencryption, this capability has to be delegated separately.
+///
+/// The rest of the parameters are the same as in [`decrypt_reencrypted()`].
+pub fn decrypt_reencrypted_hybrid(
+    decrypting_sk: &SecretKey,
+    delegating_pk: &PublicKey,
+    pq_sk: &impl PostQuantumDecapsulator,
+    hybrid_capsule: &HybridCapsule,
+    cfrags: &[CapsuleFrag],
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, ReencryptionError> {
+    let key_seed = hybrid_capsule
+        .capsule()
+        .open_reencrypted(decrypting_sk, delegating_pk, cfrags)
+        .map_err(ReencryptionError::OnOpen)?;
+    let pq_secret = pq_sk
+        .decapsulate(hybrid_capsule.pq_ciphertext())
+        .ok_or(ReencryptionError::OnPostQuantumDecapsulation)?;
+    let dem = UmbralDEM::new(&hybrid_capsule.combine_key_seeds(&key_seed, &pq_secret));
+    dem.decrypt(&ciphertext, &hybrid_capsule.to_bytes())
+        .ok_or(ReencryptionError::OnDecryption)
+}
+
+/// Decrypts the ciphertext using previously reencrypted capsule fragments
+/// when the receiving key is shared between several parties
+/// (see [`split_secret_key()`](`crate::split_secret_key()`)
+/// and [`DkgParticipant`](`crate::DkgParticipant`)).
+///
+/// `receiving_pk` is the shared public key that was used in
+/// [`generate_kfrags()`](`crate::generate_kfrags()`),
+/// and `shares` are created by at least `threshold` of its holders with
+/// [`SecretKeyShare::reencrypted_decryption_share()`](`crate::SecretKeyShare::reencrypted_decryption_share()`).
+/// One can call [`DecryptionShare::verify_reencrypted()`] to check their integrity.
+///
+/// The rest of the parameters are the same as in [`decrypt_reencrypted()`].
+pub fn decrypt_reencrypted_threshold(
+    receiving_pk: &PublicKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    cfrags: &[CapsuleFrag],
+    shares: &[DecryptionShare],
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, ReencryptionError> {
+    let dh_point =
+        combine_decryption_shares(shares).map_err(ReencryptionError::OnShareCombination)?;
+    let key_seed = capsule
+        .open_reencrypted_with_dh_point(receiving_pk, &dh_point, delegating_pk, cfrags)
+        .map_err(ReencryptionError::OnOpen)?;
+    let dem = UmbralDEM::new(&key_seed.to_array());
+    dem.decrypt(&ciphertext, &capsule.to_array())
+        .ok_or(ReencryptionError::OnDecryption)
+}
+
+/// Decrypts the ciphertext using previously reencrypted capsule fragments,
+/// some of which may be corrupted.
+///
+/// Returns the plaintext along with the IDs of the key fragments
+/// (see [`CapsuleFrag::kfrag_id()`]) the faulty capsule fragments were supposedly created with.
+///
+/// If `signing_pk` is given, every fragment is checked with [`CapsuleFrag::verify()`],
+/// and the ones that pass are used for decryption.
+/// Otherwise, subsets of `threshold` fragments are tried until one of them
+/// opens the capsule successfully, and the remaining fragments are checked against it.
+/// The latter approach can be slow if many fragments are corrupted,
+/// since the number of subsets grows combinatorially.
+///
+/// The rest of the parameters are the same as in [`decrypt_reencrypted()`].
+#[allow(clippy::type_complexity)]
+pub fn decrypt_reencrypted_robust(
+    decrypting_sk: &SecretKey,
+    delegating_pk: &PublicKey,
+    signing_pk: Option<&PublicKey>,
+    capsule: &Capsule,
+    cfrags: &[CapsuleFrag],
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<(Box<[u8]>, Box<[KeyFragID]>), ReencryptionError> {
+    let (key_seed, faulty) = match signing_pk {
+        Some(signing_pk) => {
+            let receiving_pk = PublicKey::from_secret_key(decrypting_sk);
+            let mut valid = Vec::<CapsuleFrag>::new();
+            let mut faulty = Vec::<KeyFragID>::new();
+            for cfrag in cfrags {
+                if !cfrag.verify(capsule, delegating_pk, &receiving_pk, signing_pk) {
+                    faulty.push(cfrag.kfrag_id());
+                } else if !valid
+                    .iter()
+                    .any(|other| other.kfrag_id() == cfrag.kfrag_id())
+                {
+                    valid.push(cfrag.clone());
+                }
+            }
+            let key_seed = capsule
+                .open_reencrypted(decrypting_sk, delegating_pk, &valid)
+                .map_err(ReencryptionError::OnOpen)?;
+            (key_seed, faulty)
+        }
+        None => capsule
+            .open_reencrypted_robust(decrypting_sk, delegating_pk, cfrags)
+            .map_err(ReencryptionError::OnOpen)?,
+    };
+
+    let dem = UmbralDEM::new(&key_seed.to_array());
+    let plaintext = dem
+        .decrypt(&ciphertext, &capsule.to_array())
+        .ok_or(ReencryptionError::OnDecryption)?;
+    Ok((plaintext, faulty.into_boxed_slice()))
 }
 
 #[cfg(test)]
 mod tests {
 
-    use super::{decrypt_original, decrypt_reencrypted, encrypt, reencrypt};
+    use super::{
+        decrypt_original, decrypt_original_hybrid, decrypt_original_signed, decrypt_reencrypted,
+        decrypt_reencrypted_hybrid, decrypt_reencrypted_robust, decrypt_reencrypted_signed,
+        decrypt_reencrypted_threshold, encrypt, encrypt_deterministic, encrypt_hybrid,
+        encrypt_signed, reencrypt, reencrypt_at, ReencryptionError,
+    };
+    use crate::capsule::OpenReencryptedError;
 
-    use crate::key_frag::generate_kfrags;
+    use crate::hashing::HashingMode;
+    use crate::key_frag::{
+        generate_kfrags, generate_kfrags_with_options, refresh_kfrags, KeyFragOptions,
+        SignatureScheme, ValidityPeriod,
+    };
 
     use crate::capsule_frag::CapsuleFrag;
+    use crate::threshold_decryption::{
+        split_secret_key, DecryptionShare, ThresholdDecryptionError,
+    };
+
+    use crate::hybrid::{PostQuantumDecapsulator, PostQuantumEncapsulator, PqEncapsulation};
 
+    use alloc::boxed::Box;
     use alloc::vec::Vec;
 
-    use crate::{Parameters, PublicKey, SecretKey};
+    use crate::{Parameters, PublicKey, SecretKey, SerializableToArray};
 
     #[test]
     fn test_simple_api() {
@@ -162,5 +598,623 @@ mod tests {
         )
         .unwrap();
         assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        // Bob can find out how many more cfrags he needs
+        let result = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags[0..1],
+            &ciphertext,
+        );
+        assert_eq!(
+            result,
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::NotEnoughCapsuleFrags {
+                    threshold,
+                    received: 1
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_robust_decryption() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            4,
+            true,
+            true,
+        );
+
+        let mut cfrags: Vec<CapsuleFrag> = kfrags
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+
+        // Corrupt one of the cfrags by swapping its points
+        let corrupted_id = cfrags[0].kfrag_id();
+        let cfrag = &mut cfrags[0];
+        core::mem::swap(&mut cfrag.point_e1, &mut cfrag.point_v1);
+
+        assert!(decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext
+        )
+        .is_err());
+
+        // With the verification key
+        let (plaintext_bob, faulty) = decrypt_reencrypted_robust(
+            &receiving_sk,
+            &delegating_pk,
+            Some(&signing_pk),
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+        assert_eq!(&faulty as &[_], &[corrupted_id]);
+
+        // Without the verification key
+        let (plaintext_bob, faulty) = decrypt_reencrypted_robust(
+            &receiving_sk,
+            &delegating_pk,
+            None,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+        assert_eq!(&faulty as &[_], &[corrupted_id]);
+
+        // Not enough correct cfrags
+        let result = decrypt_reencrypted_robust(
+            &receiving_sk,
+            &delegating_pk,
+            None,
+            &capsule,
+            &cfrags[0..2],
+            &ciphertext,
+        );
+        assert_eq!(
+            result,
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+    }
+
+    #[test]
+    fn test_time_bounded_reencryption() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let validity = ValidityPeriod::new(Some(100), Some(200)).unwrap();
+        assert_eq!(validity.not_before(), Some(100));
+        assert_eq!(validity.not_after(), Some(200));
+        assert_eq!(ValidityPeriod::unbounded().not_after(), None);
+        assert!(ValidityPeriod::new(Some(200), Some(100)).is_none());
+        assert!(ValidityPeriod::new(Some(100), Some(100)).is_some());
+
+        let (kfrags, _refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+            &KeyFragOptions::new().validity(&validity),
+        );
+        assert!(kfrags.iter().all(|kfrag| kfrag.validity() == validity
+            && kfrag.verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk))));
+
+        assert!(reencrypt_at(&capsule, &kfrags[0], None, 99).is_none());
+        assert!(reencrypt_at(&capsule, &kfrags[0], None, 201).is_none());
+
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt_at(&capsule, kfrag, None, 200).unwrap())
+            .collect();
+
+        for cfrag in cfrags.iter() {
+            assert!(cfrag.verify(&capsule, &delegating_pk, &receiving_pk, &signing_pk));
+            assert_eq!(cfrag.validity(), validity);
+        }
+
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[test]
+    fn test_keccak_hashing_mode() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let (kfrags, _refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+            &KeyFragOptions::new().hashing_mode(HashingMode::Keccak256),
+        );
+        assert!(kfrags
+            .iter()
+            .all(|kfrag| kfrag.hashing_mode() == HashingMode::Keccak256
+                && kfrag.verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk))));
+
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+
+        for cfrag in cfrags.iter() {
+            assert!(cfrag.verify(&capsule, &delegating_pk, &receiving_pk, &signing_pk));
+            assert_eq!(cfrag.hashing_mode(), HashingMode::Keccak256);
+
+            let cfrag_back = CapsuleFrag::from_array(&cfrag.to_array()).unwrap();
+            assert_eq!(cfrag, &cfrag_back);
+
+            // The proof does not hold if checked in a different mode
+            let mut wrong_mode = cfrag.clone();
+            wrong_mode.proof.hashing_mode = HashingMode::Sha256;
+            assert!(!wrong_mode.verify(&capsule, &delegating_pk, &receiving_pk, &signing_pk));
+        }
+
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[test]
+    fn test_combined_kfrag_options() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let validity = ValidityPeriod::new(Some(100), None).unwrap();
+        let options = KeyFragOptions::new()
+            .validity(&validity)
+            .hashing_mode(HashingMode::Keccak256)
+            .signature_scheme(SignatureScheme::Schnorr);
+        let (kfrags, refresh_key) = generate_kfrags_with_options(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+            &options,
+        );
+
+        // All the options survive a refresh
+        let kfrags = refresh_kfrags(&refresh_key, &signing_sk, &kfrags).unwrap();
+        for kfrag in kfrags.iter() {
+            assert_eq!(kfrag.validity(), validity);
+            assert_eq!(kfrag.hashing_mode(), HashingMode::Keccak256);
+            assert_eq!(kfrag.signature_scheme(), SignatureScheme::Schnorr);
+            assert!(kfrag.verify(&signing_pk, Some(&delegating_pk), Some(&receiving_pk)));
+        }
+
+        assert!(reencrypt_at(&capsule, &kfrags[0], None, 99).is_none());
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt_at(&capsule, kfrag, None, 100).unwrap())
+            .collect();
+
+        for cfrag in cfrags.iter() {
+            assert!(cfrag.verify(&capsule, &delegating_pk, &receiving_pk, &signing_pk));
+        }
+
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[test]
+    fn test_threshold_receiver() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+
+        // The receiving key is shared between three devices, two of which are needed.
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+        let key_shares = split_secret_key(&receiving_sk, 2, 3).unwrap();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+
+        let shares: Vec<DecryptionShare> = key_shares[1..]
+            .iter()
+            .map(|key_share| key_share.reencrypted_decryption_share(&cfrags[0]))
+            .collect();
+        for (share, key_share) in shares.iter().zip(key_shares[1..].iter()) {
+            assert!(share.verify_reencrypted(&cfrags[1], &key_share.public_share()));
+            assert!(!share.verify(&capsule, &key_share.public_share()));
+        }
+
+        let plaintext_bob = decrypt_reencrypted_threshold(
+            &receiving_pk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &shares,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        assert_eq!(
+            decrypt_reencrypted_threshold(
+                &receiving_pk,
+                &delegating_pk,
+                &capsule,
+                &cfrags,
+                &shares[..1],
+                &ciphertext,
+            ),
+            Err(ReencryptionError::OnShareCombination(
+                ThresholdDecryptionError::NotEnoughDecryptionShares {
+                    threshold: 2,
+                    received: 1
+                }
+            ))
+        );
+
+        // Shares of the capsule opening cannot be used in place of the receiving key shares.
+        let wrong_shares: Vec<DecryptionShare> = key_shares[1..]
+            .iter()
+            .map(|key_share| key_share.decryption_share(&capsule))
+            .collect();
+        assert_eq!(
+            decrypt_reencrypted_threshold(
+                &receiving_pk,
+                &delegating_pk,
+                &capsule,
+                &cfrags,
+                &wrong_shares,
+                &ciphertext,
+            ),
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+    }
+
+    #[test]
+    fn test_deterministic_encryption() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let plaintext = b"peace at dawn";
+        let dedup_key = b"backup dedup key";
+        let (capsule, ciphertext) =
+            encrypt_deterministic(&params, &delegating_pk, plaintext, dedup_key).unwrap();
+
+        // Same inputs produce the same result
+        let (capsule2, ciphertext2) =
+            encrypt_deterministic(&params, &delegating_pk, plaintext, dedup_key).unwrap();
+        assert_eq!(capsule, capsule2);
+        assert_eq!(ciphertext, ciphertext2);
+
+        // Changing any of the inputs changes the result
+        let (capsule3, ciphertext3) =
+            encrypt_deterministic(&params, &delegating_pk, plaintext, b"other key").unwrap();
+        assert_ne!(capsule, capsule3);
+        assert_ne!(ciphertext, ciphertext3);
+
+        let (capsule4, _ciphertext4) =
+            encrypt_deterministic(&params, &delegating_pk, b"peace at dusk", dedup_key).unwrap();
+        assert_ne!(capsule, capsule4);
+
+        let other_pk = PublicKey::from_secret_key(&SecretKey::random());
+        let (capsule5, _ciphertext5) =
+            encrypt_deterministic(&params, &other_pk, plaintext, dedup_key).unwrap();
+        assert_ne!(capsule, capsule5);
+
+        // The result is compatible with the rest of the API
+        let plaintext_alice = decrypt_original(&delegating_sk, &capsule, &ciphertext).unwrap();
+        assert_eq!(&plaintext_alice as &[u8], plaintext);
+
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[test]
+    fn test_signed_encryption() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let sender_sk = SecretKey::random();
+        let sender_pk = PublicKey::from_secret_key(&sender_sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) =
+            encrypt_signed(&params, &delegating_pk, &sender_sk, plaintext).unwrap();
+
+        // Alice learns the sender
+        let (plaintext_alice, sender_pk_alice) =
+            decrypt_original_signed(&delegating_sk, &capsule, &ciphertext).unwrap();
+        assert_eq!(&plaintext_alice as &[u8], plaintext);
+        assert_eq!(sender_pk_alice, sender_pk);
+
+        // The signature survives reencryption
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+        let (plaintext_bob, sender_pk_bob) = decrypt_reencrypted_signed(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+        assert_eq!(sender_pk_bob, sender_pk);
+
+        // An unsigned ciphertext decrypts, but fails the sender verification
+        let (capsule2, ciphertext2) = encrypt(&params, &delegating_pk, plaintext).unwrap();
+        assert!(decrypt_original_signed(&delegating_sk, &capsule2, &ciphertext2).is_none());
+
+        let cfrags2: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule2, kfrag, None))
+            .collect();
+        let result = decrypt_reencrypted_signed(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule2,
+            &cfrags2,
+            &ciphertext2,
+        );
+        assert_eq!(result, Err(ReencryptionError::SenderVerificationFailed));
+
+        // The signature is bound to the capsule it was created with:
+        // re-encrypting the same signed payload under a new capsule invalidates it
+        let payload = decrypt_original(&delegating_sk, &capsule, &ciphertext).unwrap();
+        let (capsule3, ciphertext3) = encrypt(&params, &delegating_pk, &payload).unwrap();
+        assert!(decrypt_original_signed(&delegating_sk, &capsule3, &ciphertext3).is_none());
+
+        // The plain decryption still works, returning the signed payload
+        let payload_plain = decrypt_original(&delegating_sk, &capsule3, &ciphertext3).unwrap();
+        assert_eq!(payload_plain, payload);
+    }
+
+    // A stand-in for a post-quantum KEM: the "ciphertext" is the shared secret
+    // masked with the key. It is obviously insecure, but it lets us test the hybrid flow.
+    struct MockPqKey(u8);
+
+    impl PostQuantumEncapsulator for MockPqKey {
+        fn encapsulate(&self) -> Option<PqEncapsulation> {
+            let secret = SecretKey::random().to_array();
+            Some(PqEncapsulation {
+                ciphertext: secret.iter().map(|b| b ^ self.0).collect(),
+                shared_secret: secret.to_vec().into_boxed_slice(),
+            })
+        }
+    }
+
+    impl PostQuantumDecapsulator for MockPqKey {
+        fn decapsulate(&self, ciphertext: &[u8]) -> Option<Box<[u8]>> {
+            Some(ciphertext.iter().map(|b| b ^ self.0).collect())
+        }
+    }
+
+    #[test]
+    fn test_hybrid_encryption() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let pq_key = MockPqKey(0x5a);
+
+        let plaintext = b"peace at dawn";
+        let (hybrid_capsule, ciphertext) =
+            encrypt_hybrid(&params, &delegating_pk, &pq_key, plaintext).unwrap();
+
+        let plaintext_alice =
+            decrypt_original_hybrid(&delegating_sk, &pq_key, &hybrid_capsule, &ciphertext).unwrap();
+        assert_eq!(&plaintext_alice as &[u8], plaintext);
+
+        // Both shared secrets are required
+        let wrong_pq_key = MockPqKey(0xa5);
+        assert!(decrypt_original_hybrid(
+            &delegating_sk,
+            &wrong_pq_key,
+            &hybrid_capsule,
+            &ciphertext
+        )
+        .is_none());
+        assert!(decrypt_original(&delegating_sk, hybrid_capsule.capsule(), &ciphertext).is_none());
+
+        // The Umbral part is reencrypted as usual
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(hybrid_capsule.capsule(), kfrag, None))
+            .collect();
+
+        let plaintext_bob = decrypt_reencrypted_hybrid(
+            &receiving_sk,
+            &delegating_pk,
+            &pq_key,
+            &hybrid_capsule,
+            &cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        let result = decrypt_reencrypted_hybrid(
+            &receiving_sk,
+            &delegating_pk,
+            &wrong_pq_key,
+            &hybrid_capsule,
+            &cfrags,
+            &ciphertext,
+        );
+        assert_eq!(result, Err(ReencryptionError::OnDecryption));
     }
 }
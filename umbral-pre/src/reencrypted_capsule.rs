@@ -0,0 +1,256 @@
+use crate::capsule::{Capsule, OpenReencryptedError};
+use crate::capsule_frag::CapsuleFrag;
+use crate::curve::{CurvePoint, PublicKey, SecretKey};
+use crate::dem::UmbralDEM;
+use crate::hashing_ds::hash_to_shared_secret;
+use crate::pre::ReencryptionError;
+use crate::traits::SerializableToArray;
+
+use alloc::boxed::Box;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use typenum::op;
+
+/// The result of combining reencrypted capsule fragments for a [`Capsule`].
+///
+/// The receiving party can store it instead of the fragments
+/// and decrypt the ciphertext with [`decrypt()`](`Self::decrypt()`)
+/// without combining them again, or use it to re-delegate access to someone else.
+///
+/// Capsule fragments created from it with
+/// [`reencrypt_redelegated()`](`crate::reencrypt_redelegated()`)
+/// can be decrypted by the new receiving party with
+/// [`decrypt_reencrypted()`](`crate::decrypt_reencrypted()`),
+/// passing the original capsule (see [`capsule()`](`Self::capsule()`))
+/// and the original encrypting party's public key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReencryptedCapsule {
+    pub(crate) capsule: Capsule,
+    pub(crate) point_e_prime: CurvePoint,
+    pub(crate) point_v_prime: CurvePoint,
+    pub(crate) precursor: CurvePoint,
+}
+
+type CapsuleSize = <Capsule as SerializableToArray>::Size;
+type PointSize = <CurvePoint as SerializableToArray>::Size;
+type ReencryptedCapsuleSize = op!(CapsuleSize + PointSize + PointSize + PointSize);
+
+impl SerializableToArray for ReencryptedCapsule {
+    type Size = ReencryptedCapsuleSize;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.capsule
+            .to_array()
+            .concat(self.point_e_prime.to_array())
+            .concat(self.point_v_prime.to_array())
+            .concat(self.precursor.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (capsule, rest) = Capsule::take(*arr)?;
+        let (point_e_prime, rest) = CurvePoint::take(rest)?;
+        let (point_v_prime, rest) = CurvePoint::take(rest)?;
+        let precursor = CurvePoint::take_last(rest)?;
+        Some(Self {
+            capsule,
+            point_e_prime,
+            point_v_prime,
+            precursor,
+        })
+    }
+}
+
+impl ReencryptedCapsule {
+    /// Combines capsule fragments, checking the result against the capsule.
+    ///
+    /// The parameters are the same as in [`decrypt_reencrypted()`](`crate::decrypt_reencrypted()`);
+    /// in particular, `delegating_pk` is always the public key of the original encrypting party,
+    /// even if `cfrags` were themselves created from a [`ReencryptedCapsule`].
+    pub fn new(
+        capsule: &Capsule,
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        cfrags: &[CapsuleFrag],
+    ) -> Result<Self, OpenReencryptedError> {
+        let (point_e_prime, point_v_prime, _d) =
+            capsule.combine_cfrags(receiving_sk, delegating_pk, cfrags)?;
+        Ok(Self {
+            capsule: *capsule,
+            point_e_prime,
+            point_v_prime,
+            // `combine_cfrags()` ensures the precursors are the same for all fragments.
+            precursor: cfrags[0].precursor,
+        })
+    }
+
+    /// Returns the original capsule.
+    pub fn capsule(&self) -> Capsule {
+        self.capsule
+    }
+
+    /// Decrypts the ciphertext encrypted along with the original capsule.
+    ///
+    /// `receiving_sk` and `delegating_pk` must be the same as the ones
+    /// the reencrypted capsule was created with.
+    pub fn decrypt(
+        &self,
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        ciphertext: impl AsRef<[u8]>,
+    ) -> Result<Box<[u8]>, ReencryptionError> {
+        let pub_key = PublicKey::from_secret_key(receiving_sk).to_point();
+        let dh_point = &self.precursor * &receiving_sk.to_secret_scalar();
+        let d = hash_to_shared_secret(&self.precursor, &pub_key, &dh_point);
+
+        self.capsule
+            .verify_combined(delegating_pk, &self.point_e_prime, &self.point_v_prime, &d)
+            .map_err(ReencryptionError::OnOpen)?;
+
+        let key_seed = &(&self.point_e_prime + &self.point_v_prime) * &d;
+        let dem = UmbralDEM::new(&key_seed.to_array());
+        dem.decrypt(ciphertext, &self.capsule.to_array())
+            .ok_or(ReencryptionError::OnDecryption)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::ReencryptedCapsule;
+    use crate::{
+        decrypt_reencrypted, encrypt, generate_kfrags, generate_redelegation_kfrags, reencrypt,
+        reencrypt_redelegated, CapsuleFrag, OpenReencryptedError, Parameters, PublicKey,
+        ReencryptionError, SecretKey, SerializableToArray,
+    };
+
+    #[test]
+    fn test_redelegation() {
+        let params = Parameters::new();
+
+        let alice_sk = SecretKey::random();
+        let alice_pk = PublicKey::from_secret_key(&alice_sk);
+        let alice_signing_sk = SecretKey::random();
+        let alice_signing_pk = PublicKey::from_secret_key(&alice_signing_sk);
+
+        let bob_sk = SecretKey::random();
+        let bob_pk = PublicKey::from_secret_key(&bob_sk);
+        let bob_signing_sk = SecretKey::random();
+        let bob_signing_pk = PublicKey::from_secret_key(&bob_signing_sk);
+
+        let carol_sk = SecretKey::random();
+        let carol_pk = PublicKey::from_secret_key(&carol_sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &alice_pk, plaintext).unwrap();
+
+        // First hop: Alice -> Bob
+        let kfrags = generate_kfrags(
+            &params,
+            &alice_sk,
+            &bob_pk,
+            &alice_signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        let cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&capsule, kfrag, None))
+            .collect();
+        assert!(cfrags.iter().all(|cfrag| cfrag.verify(
+            &capsule,
+            &alice_pk,
+            &bob_pk,
+            &alice_signing_pk
+        )));
+
+        let rcapsule = ReencryptedCapsule::new(&capsule, &bob_sk, &alice_pk, &cfrags).unwrap();
+
+        let rcapsule_arr = rcapsule.to_array();
+        let rcapsule_back = ReencryptedCapsule::from_array(&rcapsule_arr).unwrap();
+        assert_eq!(rcapsule, rcapsule_back);
+
+        // Bob can decrypt with the stored object without the fragments
+        let plaintext_bob = rcapsule_back
+            .decrypt(&bob_sk, &alice_pk, &ciphertext)
+            .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+        assert_eq!(
+            rcapsule_back.decrypt(&carol_sk, &alice_pk, &ciphertext),
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+
+        // Second hop: Bob -> Carol
+        let kfrags2 = generate_redelegation_kfrags(
+            &bob_sk,
+            &rcapsule,
+            &carol_pk,
+            &bob_signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        assert!(kfrags2.iter().all(|kfrag| kfrag.verify(
+            &bob_signing_pk,
+            Some(&bob_pk),
+            Some(&carol_pk)
+        )));
+
+        let cfrags2: Vec<CapsuleFrag> = kfrags2[1..3]
+            .iter()
+            .map(|kfrag| reencrypt_redelegated(&rcapsule, kfrag, None))
+            .collect();
+        assert!(cfrags2.iter().all(|cfrag| cfrag.verify_redelegated(
+            &rcapsule,
+            &bob_pk,
+            &carol_pk,
+            &bob_signing_pk
+        )));
+        // Second-level fragments do not verify as first-level ones
+        assert!(!cfrags2[0].verify(&capsule, &bob_pk, &carol_pk, &bob_signing_pk));
+
+        let plaintext_carol = decrypt_reencrypted(
+            &carol_sk,
+            &alice_pk,
+            &rcapsule.capsule(),
+            &cfrags2,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_carol as &[u8], plaintext);
+
+        // Bob cannot use Carol's fragments
+        assert_eq!(
+            decrypt_reencrypted(&bob_sk, &alice_pk, &capsule, &cfrags2, &ciphertext),
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+
+        // Second-level fragments for a different capsule do not open this one
+        let (other_capsule, _ciphertext) = encrypt(&params, &alice_pk, plaintext).unwrap();
+        let other_cfrags: Vec<CapsuleFrag> = kfrags[0..2]
+            .iter()
+            .map(|kfrag| reencrypt(&other_capsule, kfrag, None))
+            .collect();
+        let other_rcapsule =
+            ReencryptedCapsule::new(&other_capsule, &bob_sk, &alice_pk, &other_cfrags).unwrap();
+        let other_cfrags2: Vec<CapsuleFrag> = kfrags2[1..3]
+            .iter()
+            .map(|kfrag| reencrypt_redelegated(&other_rcapsule, kfrag, None))
+            .collect();
+        assert_eq!(
+            decrypt_reencrypted(&carol_sk, &alice_pk, &capsule, &other_cfrags2, &ciphertext),
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+    }
+}
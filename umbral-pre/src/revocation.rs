@@ -0,0 +1,244 @@
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::curve::{CurvePoint, PublicKey, SecretKey, Signature};
+use crate::hashing::SignatureDigest;
+use crate::key_frag::{KeyFrag, KeyFragID};
+use crate::pre::reencrypt;
+use crate::traits::SerializableToArray;
+
+use alloc::vec::Vec;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use typenum::{op, U1};
+
+/// A notice from the delegating party telling a proxy to stop using a key fragment,
+/// or all the key fragments of a delegation.
+///
+/// It is signed with the same key the key fragments were signed with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Revocation {
+    kfrag_id: KeyFragID,
+    precursor: CurvePoint,
+    whole_delegation: bool,
+    signature: Signature,
+}
+
+type KeyFragIDSize = <KeyFragID as SerializableToArray>::Size;
+type PointSize = <CurvePoint as SerializableToArray>::Size;
+type SignatureSize = <Signature as SerializableToArray>::Size;
+type RevocationSize = op!(KeyFragIDSize + PointSize + U1 + SignatureSize);
+
+impl SerializableToArray for Revocation {
+    type Size = RevocationSize;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.kfrag_id
+            .to_array()
+            .concat(self.precursor.to_array())
+            .concat(self.whole_delegation.to_array())
+            .concat(self.signature.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (kfrag_id, rest) = KeyFragID::take(*arr)?;
+        let (precursor, rest) = CurvePoint::take(rest)?;
+        let (whole_delegation, rest) = bool::take(rest)?;
+        let signature = Signature::take_last(rest)?;
+        Some(Self {
+            kfrag_id,
+            precursor,
+            whole_delegation,
+            signature,
+        })
+    }
+}
+
+impl Revocation {
+    fn new(signing_sk: &SecretKey, kfrag: &KeyFrag, whole_delegation: bool) -> Self {
+        let signature =
+            Self::digest(&kfrag.id, &kfrag.precursor, whole_delegation).sign(signing_sk);
+        Self {
+            kfrag_id: kfrag.id,
+            precursor: kfrag.precursor,
+            whole_delegation,
+            signature,
+        }
+    }
+
+    /// Creates a revocation of a single key fragment.
+    pub fn for_kfrag(signing_sk: &SecretKey, kfrag: &KeyFrag) -> Self {
+        Self::new(signing_sk, kfrag, false)
+    }
+
+    /// Creates a revocation of all the key fragments produced
+    /// by the same [`generate_kfrags()`](`crate::generate_kfrags()`) call as `kfrag`.
+    pub fn for_delegation(signing_sk: &SecretKey, kfrag: &KeyFrag) -> Self {
+        Self::new(signing_sk, kfrag, true)
+    }
+
+    fn digest(
+        kfrag_id: &KeyFragID,
+        precursor: &CurvePoint,
+        whole_delegation: bool,
+    ) -> SignatureDigest {
+        // The tag keeps the signature from being mistaken for a key fragment one.
+        SignatureDigest::new()
+            .chain_bytes(b"REVOCATION")
+            .chain_bytes(kfrag_id)
+            .chain_point(precursor)
+            .chain_bool(whole_delegation)
+    }
+
+    /// Verifies the signature of the revocation.
+    pub fn verify(&self, signing_pk: &PublicKey) -> bool {
+        Self::digest(&self.kfrag_id, &self.precursor, self.whole_delegation)
+            .verify(signing_pk, &self.signature)
+    }
+
+    /// Returns `true` if the revocation covers the given key fragment.
+    ///
+    /// Does not check the signature; see [`verify()`](`Self::verify()`).
+    pub fn applies_to(&self, kfrag: &KeyFrag) -> bool {
+        self.precursor == kfrag.precursor && (self.whole_delegation || self.kfrag_id == kfrag.id)
+    }
+}
+
+/// A collection of verified revocations kept by a proxy.
+#[derive(Clone, Debug, Default)]
+pub struct RevocationSet {
+    // Revocations along with the keys they were verified with.
+    revocations: Vec<(PublicKey, Revocation)>,
+}
+
+impl RevocationSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verifies the revocation with `signing_pk` (the key the revoked key fragments
+    /// were signed with) and adds it to the set.
+    ///
+    /// Returns `false` if the verification failed.
+    pub fn add(&mut self, revocation: &Revocation, signing_pk: &PublicKey) -> bool {
+        if !revocation.verify(signing_pk) {
+            return false;
+        }
+        self.revocations.push((*signing_pk, revocation.clone()));
+        true
+    }
+
+    /// Returns `true` if the key fragment, signed with `signing_pk`, was revoked.
+    ///
+    /// The revocation must be signed with the same key as the key fragment,
+    /// since the fragment ID and the delegation it belongs to are not secret.
+    pub fn is_revoked(&self, kfrag: &KeyFrag, signing_pk: &PublicKey) -> bool {
+        self.revocations
+            .iter()
+            .any(|(pk, revocation)| pk == signing_pk && revocation.applies_to(kfrag))
+    }
+
+    /// Reencrypts the capsule with the key fragment, as [`reencrypt()`](`crate::reencrypt()`)
+    /// does, unless the fragment was revoked.
+    ///
+    /// `signing_pk` is the key the fragment was signed with
+    /// (the one used in [`KeyFrag::verify()`]).
+    /// Returns `None` if the key fragment was revoked.
+    pub fn reencrypt(
+        &self,
+        capsule: &Capsule,
+        kfrag: &KeyFrag,
+        signing_pk: &PublicKey,
+        metadata: Option<&[u8]>,
+    ) -> Option<CapsuleFrag> {
+        if self.is_revoked(kfrag, signing_pk) {
+            return None;
+        }
+        Some(reencrypt(capsule, kfrag, metadata))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Revocation, RevocationSet};
+    use crate::{encrypt, generate_kfrags, Parameters, PublicKey, SecretKey, SerializableToArray};
+
+    #[test]
+    fn test_revocation() {
+        let params = Parameters::new();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = PublicKey::from_secret_key(&delegating_sk);
+
+        let signing_sk = SecretKey::random();
+        let signing_pk = PublicKey::from_secret_key(&signing_sk);
+
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = PublicKey::from_secret_key(&receiving_sk);
+
+        let (capsule, _ciphertext) = encrypt(&params, &delegating_pk, b"peace at dawn").unwrap();
+
+        let kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+        let other_kfrags = generate_kfrags(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signing_sk,
+            2,
+            3,
+            true,
+            true,
+        );
+
+        let revocation = Revocation::for_kfrag(&signing_sk, &kfrags[0]);
+        let revocation_arr = revocation.to_array();
+        let revocation_back = Revocation::from_array(&revocation_arr).unwrap();
+        assert_eq!(revocation, revocation_back);
+
+        assert!(revocation.verify(&signing_pk));
+        assert!(!revocation.verify(&delegating_pk));
+        assert!(revocation.applies_to(&kfrags[0]));
+        assert!(!revocation.applies_to(&kfrags[1]));
+
+        let mut revocations = RevocationSet::new();
+        assert!(revocations
+            .reencrypt(&capsule, &kfrags[0], &signing_pk, None)
+            .is_some());
+
+        // A revocation signed by someone else is rejected
+        let forged_revocation = Revocation::for_delegation(&receiving_sk, &kfrags[0]);
+        assert!(!revocations.add(&forged_revocation, &signing_pk));
+        assert!(revocations.add(&forged_revocation, &receiving_pk));
+        assert!(!revocations.is_revoked(&kfrags[0], &signing_pk));
+
+        assert!(revocations.add(&revocation, &signing_pk));
+        assert!(revocations.is_revoked(&kfrags[0], &signing_pk));
+        assert!(!revocations.is_revoked(&kfrags[1], &signing_pk));
+        assert!(revocations
+            .reencrypt(&capsule, &kfrags[0], &signing_pk, None)
+            .is_none());
+        assert!(revocations
+            .reencrypt(&capsule, &kfrags[1], &signing_pk, None)
+            .is_some());
+
+        let delegation_revocation = Revocation::for_delegation(&signing_sk, &kfrags[2]);
+        assert!(revocations.add(&delegation_revocation, &signing_pk));
+        assert!(kfrags
+            .iter()
+            .all(|kfrag| revocations.is_revoked(kfrag, &signing_pk)));
+        assert!(!other_kfrags
+            .iter()
+            .any(|kfrag| revocations.is_revoked(kfrag, &signing_pk)));
+    }
+}
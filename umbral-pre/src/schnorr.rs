@@ -0,0 +1,227 @@
+//! Schnorr signatures over secp256k1 as specified in BIP-340.
+//! The backend does not support them yet, so they are implemented here
+//! on top of the curve arithmetic.
+
+use digest::Digest;
+use generic_array::sequence::{Concat, Split};
+use generic_array::GenericArray;
+use sha2::Sha256;
+use typenum::{op, U1, U32};
+
+use crate::curve::{CurvePoint, CurveScalar, PublicKey, SecretKey};
+use crate::traits::SerializableToArray;
+
+type XOnlySize = U32;
+type ScalarSize = <CurveScalar as SerializableToArray>::Size;
+
+// BIP-340 signs 32-byte messages, which in our case are digests.
+pub(crate) type SchnorrMessage = GenericArray<u8, U32>;
+
+fn tagged_hash(tag: &[u8]) -> Sha256 {
+    let tag_hash = Sha256::digest(tag);
+    Sha256::new().chain(tag_hash).chain(tag_hash)
+}
+
+/// Returns the `x` coordinate of a non-identity point, and whether its `y` coordinate is even.
+fn x_only(point: &CurvePoint) -> (GenericArray<u8, XOnlySize>, bool) {
+    let compressed = point.to_array();
+    // The SEC1 tag of a compressed point with an even `y` coordinate is 2.
+    (
+        *GenericArray::<u8, XOnlySize>::from_slice(&compressed[1..]),
+        compressed[0] == 2,
+    )
+}
+
+/// Returns the point with the given `x` coordinate and an even `y` coordinate.
+fn lift_x(x: &GenericArray<u8, XOnlySize>) -> Option<CurvePoint> {
+    let compressed = GenericArray::<u8, U1>::from([2u8]).concat(*x);
+    CurvePoint::from_array(&compressed)
+}
+
+fn negate(scalar: &CurveScalar) -> CurveScalar {
+    &CurveScalar::default() - scalar
+}
+
+fn challenge(
+    r: &GenericArray<u8, XOnlySize>,
+    px: &GenericArray<u8, XOnlySize>,
+    message: &SchnorrMessage,
+) -> CurveScalar {
+    CurveScalar::from_digest(
+        tagged_hash(b"BIP0340/challenge")
+            .chain(r)
+            .chain(px)
+            .chain(message),
+    )
+}
+
+/// A BIP-340 signature: the `x` coordinate of the nonce point followed by the scalar `s`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchnorrSignature {
+    r: GenericArray<u8, XOnlySize>,
+    s: CurveScalar,
+}
+
+impl SerializableToArray for SchnorrSignature {
+    type Size = op!(XOnlySize + ScalarSize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.r.concat(self.s.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (r, rest): (GenericArray<u8, XOnlySize>, GenericArray<u8, ScalarSize>) = (*arr).split();
+        let s = CurveScalar::take_last(rest)?;
+        Some(Self { r, s })
+    }
+}
+
+impl SchnorrSignature {
+    /// Signs the message deterministically
+    /// (that is, with the auxiliary random data set to zero).
+    pub(crate) fn new(sk: &SecretKey, message: &SchnorrMessage) -> Self {
+        let g = CurvePoint::generator();
+
+        let secret = sk.to_secret_scalar();
+        let (px, p_has_even_y) = x_only(&(&g * &secret));
+        let d = if p_has_even_y {
+            secret
+        } else {
+            negate(&secret)
+        };
+
+        let aux_hash = tagged_hash(b"BIP0340/aux").chain([0u8; 32]).finalize();
+        let mut t = d.to_array();
+        for (t_byte, aux_byte) in t.iter_mut().zip(aux_hash.iter()) {
+            *t_byte ^= aux_byte;
+        }
+
+        // The nonce is zero with a negligible probability, in which case BIP-340 fails.
+        let nonce = CurveScalar::from_digest(
+            tagged_hash(b"BIP0340/nonce")
+                .chain(t)
+                .chain(px)
+                .chain(message),
+        );
+        let (r, r_has_even_y) = x_only(&(&g * &nonce));
+        let k = if r_has_even_y { nonce } else { negate(&nonce) };
+
+        let e = challenge(&r, &px, message);
+        let s = &k + &(&e * &d);
+
+        Self { r, s }
+    }
+
+    /// Returns the points `R` and `P` and the challenge scalar `e`
+    /// participating in the verification equation `s * G == R + e * P`.
+    fn equation_terms(
+        &self,
+        pk: &PublicKey,
+        message: &SchnorrMessage,
+    ) -> Option<(CurvePoint, CurvePoint, CurveScalar)> {
+        // The signature is checked against the key with the same `x` coordinate and an even `y`.
+        let (px, _) = x_only(&pk.to_point());
+        let point_p = lift_x(&px)?;
+        let point_r = lift_x(&self.r)?;
+        let e = challenge(&self.r, &px, message);
+        Some((point_r, point_p, e))
+    }
+
+    pub(crate) fn verify(&self, pk: &PublicKey, message: &SchnorrMessage) -> bool {
+        match self.equation_terms(pk, message) {
+            Some((point_r, point_p, e)) => {
+                &CurvePoint::generator() * &self.s == &point_r + &(&point_p * &e)
+            }
+            None => false,
+        }
+    }
+
+    /// Verifies several signatures at once, checking a random linear combination
+    /// of their verification equations.
+    ///
+    /// Returns `true` if all the signatures are valid (or the list is empty),
+    /// and `false` (with an overwhelming probability) otherwise.
+    pub(crate) fn verify_batch(items: &[(&Self, &PublicKey, &SchnorrMessage)]) -> bool {
+        let mut s_sum = CurveScalar::default();
+        let mut rhs = CurvePoint::identity();
+        for (i, (signature, pk, message)) in items.iter().enumerate() {
+            let (point_r, point_p, e) = match signature.equation_terms(pk, message) {
+                Some(terms) => terms,
+                None => return false,
+            };
+            let a = if i == 0 {
+                CurveScalar::one()
+            } else {
+                CurveScalar::random_nonzero()
+            };
+            s_sum = &s_sum + &(&a * &signature.s);
+            rhs = &(&rhs + &(&point_r * &a)) + &(&point_p * &(&a * &e));
+        }
+        &CurvePoint::generator() * &s_sum == rhs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use generic_array::GenericArray;
+
+    use super::{SchnorrMessage, SchnorrSignature};
+    use crate::curve::{PublicKey, SecretKey};
+    use crate::traits::SerializableToArray;
+
+    fn from_hex<T: SerializableToArray>(hex: &str) -> T {
+        let bytes: alloc::vec::Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        T::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_bip340_vector() {
+        // Test vector 0 from BIP-340 (the auxiliary random data is zero).
+        let sk: SecretKey =
+            from_hex("0000000000000000000000000000000000000000000000000000000000000003");
+        let message = SchnorrMessage::default();
+        let expected: SchnorrSignature = from_hex(concat!(
+            "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA8215",
+            "25F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0"
+        ));
+
+        let signature = SchnorrSignature::new(&sk, &message);
+        assert_eq!(signature, expected);
+
+        let pk = PublicKey::from_secret_key(&sk);
+        assert!(signature.verify(&pk, &message));
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let sk = SecretKey::random();
+        let pk = PublicKey::from_secret_key(&sk);
+        let message = GenericArray::clone_from_slice(&[7u8; 32]);
+        let other_message = GenericArray::clone_from_slice(&[8u8; 32]);
+
+        let signature = SchnorrSignature::new(&sk, &message);
+        assert_eq!(signature, SchnorrSignature::new(&sk, &message));
+        assert!(signature.verify(&pk, &message));
+        assert!(!signature.verify(&pk, &other_message));
+
+        let signature_back = SchnorrSignature::from_array(&signature.to_array()).unwrap();
+        assert_eq!(signature, signature_back);
+
+        let other_sk = SecretKey::random();
+        let other_pk = PublicKey::from_secret_key(&other_sk);
+        let other_signature = SchnorrSignature::new(&other_sk, &other_message);
+
+        assert!(SchnorrSignature::verify_batch(&[
+            (&signature, &pk, &message),
+            (&other_signature, &other_pk, &other_message),
+        ]));
+        assert!(!SchnorrSignature::verify_batch(&[
+            (&signature, &pk, &message),
+            (&other_signature, &other_pk, &message),
+        ]));
+    }
+}
@@ -0,0 +1,456 @@
+use crate::capsule::{lambda_coeff, Capsule};
+use crate::capsule_frag::CapsuleFrag;
+use crate::curve::{CurvePoint, CurveScalar, PublicKey, SecretKey};
+use crate::hashing::ScalarDigest;
+use crate::key_frag::poly_eval;
+use crate::traits::SerializableToArray;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use typenum::op;
+
+/// Errors that can happen when combining [`DecryptionShare`] objects.
+#[derive(Debug, PartialEq)]
+pub enum ThresholdDecryptionError {
+    /// An empty decryption share list is given.
+    NoDecryptionShares,
+    /// Decryption shares are mismatched (created with different thresholds).
+    MismatchedDecryptionShares,
+    /// Some of the given decryption shares are repeated.
+    RepeatingDecryptionShares,
+    /// Fewer decryption shares than the threshold of the key shares are given.
+    NotEnoughDecryptionShares {
+        /// The number of shares necessary for decryption.
+        threshold: usize,
+        /// The number of distinct shares given.
+        received: usize,
+    },
+    /// An error when decrypting the ciphertext with the key recovered from the shares.
+    /// Can be caused by an incorrect decryption share
+    /// (see [`DecryptionShare::verify()`]).
+    OnDecryption,
+}
+
+impl fmt::Display for ThresholdDecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDecryptionShares => write!(f, "Empty DecryptionShare sequence"),
+            Self::MismatchedDecryptionShares => {
+                write!(f, "DecryptionShares are not pairwise consistent")
+            }
+            Self::RepeatingDecryptionShares => {
+                write!(f, "Some of the DecryptionShares are repeated")
+            }
+            Self::NotEnoughDecryptionShares {
+                threshold,
+                received,
+            } => write!(
+                f,
+                "Not enough DecryptionShares: need {} more (threshold is {})",
+                threshold - received,
+                threshold
+            ),
+            Self::OnDecryption => write!(f, "Threshold decryption error on decryption"),
+        }
+    }
+}
+
+type U32Size = <u32 as SerializableToArray>::Size;
+type PointSize = <CurvePoint as SerializableToArray>::Size;
+type ScalarSize = <CurveScalar as SerializableToArray>::Size;
+type SecretKeySize = <SecretKey as SerializableToArray>::Size;
+type PublicKeySize = <PublicKey as SerializableToArray>::Size;
+
+/// A share of a secret key held by a member of a committee.
+///
+/// Any `threshold` members can jointly open a [`Capsule`] encrypted with the shared key
+/// (see [`decrypt_original_threshold()`](`crate::decrypt_original_threshold()`))
+/// without reconstructing the key itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecretKeyShare {
+    index: u32,
+    threshold: u32,
+    pub(crate) key: SecretKey,
+}
+
+impl SerializableToArray for SecretKeyShare {
+    type Size = op!(U32Size + U32Size + SecretKeySize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.index
+            .to_array()
+            .concat(self.threshold.to_array())
+            .concat(self.key.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (index, rest) = u32::take(*arr)?;
+        let (threshold, rest) = u32::take(rest)?;
+        let key = SecretKey::take_last(rest)?;
+        if index == 0 || threshold == 0 {
+            return None;
+        }
+        Some(Self {
+            index,
+            threshold,
+            key,
+        })
+    }
+}
+
+impl SecretKeyShare {
+    pub(crate) fn new(index: u32, threshold: u32, key: SecretKey) -> Self {
+        Self {
+            index,
+            threshold,
+            key,
+        }
+    }
+
+    /// Returns the index of the share (the point the sharing polynomial is evaluated at).
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the number of shares necessary for decryption.
+    pub fn threshold(&self) -> usize {
+        self.threshold as usize
+    }
+
+    /// Returns the public counterpart of the share,
+    /// used to verify the [`DecryptionShare`] objects it produces.
+    pub fn public_share(&self) -> PublicKeyShare {
+        PublicKeyShare {
+            index: self.index,
+            threshold: self.threshold,
+            key: PublicKey::from_secret_key(&self.key),
+        }
+    }
+
+    /// Creates a partial opening of the capsule
+    /// along with a proof of its correctness.
+    pub fn decryption_share(&self, capsule: &Capsule) -> DecryptionShare {
+        DecryptionShare::new(&(&capsule.point_e + &capsule.point_v), self)
+    }
+
+    /// Creates a partial opening of the precursor of the capsule fragments
+    /// reencrypted for the shared key, along with a proof of its correctness.
+    ///
+    /// Any of the fragments to be combined can be given, since they share the precursor.
+    /// See [`decrypt_reencrypted_threshold()`](`crate::decrypt_reencrypted_threshold()`).
+    pub fn reencrypted_decryption_share(&self, cfrag: &CapsuleFrag) -> DecryptionShare {
+        DecryptionShare::new(&cfrag.precursor, self)
+    }
+}
+
+/// The public counterpart of a [`SecretKeyShare`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PublicKeyShare {
+    pub(crate) index: u32,
+    threshold: u32,
+    pub(crate) key: PublicKey,
+}
+
+impl SerializableToArray for PublicKeyShare {
+    type Size = op!(U32Size + U32Size + PublicKeySize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.index
+            .to_array()
+            .concat(self.threshold.to_array())
+            .concat(self.key.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (index, rest) = u32::take(*arr)?;
+        let (threshold, rest) = u32::take(rest)?;
+        let key = PublicKey::take_last(rest)?;
+        Some(Self {
+            index,
+            threshold,
+            key,
+        })
+    }
+}
+
+impl PublicKeyShare {
+    pub(crate) fn new(index: u32, threshold: u32, key: PublicKey) -> Self {
+        Self {
+            index,
+            threshold,
+            key,
+        }
+    }
+
+    /// Returns the index of the share.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// A partial opening of a [`Capsule`] (or of the precursor of [`CapsuleFrag`] objects)
+/// created by a holder of a [`SecretKeyShare`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecryptionShare {
+    index: u32,
+    threshold: u32,
+    point_p1: CurvePoint,
+    point_p2: CurvePoint,
+    point_g2: CurvePoint,
+    signature: CurveScalar,
+}
+
+impl SerializableToArray for DecryptionShare {
+    type Size = op!(U32Size + U32Size + PointSize + PointSize + PointSize + ScalarSize);
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.index
+            .to_array()
+            .concat(self.threshold.to_array())
+            .concat(self.point_p1.to_array())
+            .concat(self.point_p2.to_array())
+            .concat(self.point_g2.to_array())
+            .concat(self.signature.to_array())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let (index, rest) = u32::take(*arr)?;
+        let (threshold, rest) = u32::take(rest)?;
+        let (point_p1, rest) = CurvePoint::take(rest)?;
+        let (point_p2, rest) = CurvePoint::take(rest)?;
+        let (point_g2, rest) = CurvePoint::take(rest)?;
+        let signature = CurveScalar::take_last(rest)?;
+        Some(Self {
+            index,
+            threshold,
+            point_p1,
+            point_p2,
+            point_g2,
+            signature,
+        })
+    }
+}
+
+fn challenge(
+    p: &CurvePoint,
+    p1: &CurvePoint,
+    p2: &CurvePoint,
+    pk: &CurvePoint,
+    g2: &CurvePoint,
+) -> CurveScalar {
+    ScalarDigest::new_with_dst(b"DECRYPTION_SHARE")
+        .chain_points(&[*p, *p1, *p2, CurvePoint::generator(), *pk, *g2])
+        .finalize()
+}
+
+impl DecryptionShare {
+    /// Multiplies the base point `p` by the key share.
+    fn new(p: &CurvePoint, key_share: &SecretKeyShare) -> Self {
+        let g = CurvePoint::generator();
+        let x = key_share.key.to_secret_scalar();
+
+        let p1 = p * &x;
+
+        // A proof of equality of discrete logarithms of `p1` to the base `p`
+        // and the public share to the base `g`.
+        let t = CurveScalar::random_nonzero();
+        let p2 = p * &t;
+        let g2 = &g * &t;
+
+        let pk = PublicKey::from_secret_key(&key_share.key).to_point();
+        let h = challenge(p, &p1, &p2, &pk, &g2);
+        let z = &t + &(&x * &h);
+
+        Self {
+            index: key_share.index,
+            threshold: key_share.threshold,
+            point_p1: p1,
+            point_p2: p2,
+            point_g2: g2,
+            signature: z,
+        }
+    }
+
+    /// Returns the index of the key share the decryption share was created with.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Verifies that the decryption share was created for the given capsule
+    /// with the secret counterpart of `public_share`.
+    pub fn verify(&self, capsule: &Capsule, public_share: &PublicKeyShare) -> bool {
+        self.verify_for_base(&(&capsule.point_e + &capsule.point_v), public_share)
+    }
+
+    /// Verifies that the decryption share was created for the precursor of the given
+    /// capsule fragment with the secret counterpart of `public_share`
+    /// (see [`SecretKeyShare::reencrypted_decryption_share()`]).
+    pub fn verify_reencrypted(&self, cfrag: &CapsuleFrag, public_share: &PublicKeyShare) -> bool {
+        self.verify_for_base(&cfrag.precursor, public_share)
+    }
+
+    fn verify_for_base(&self, p: &CurvePoint, public_share: &PublicKeyShare) -> bool {
+        if self.index != public_share.index || self.threshold != public_share.threshold {
+            return false;
+        }
+
+        let g = CurvePoint::generator();
+        let pk = public_share.key.to_point();
+
+        let p1 = self.point_p1;
+        let p2 = self.point_p2;
+        let g2 = self.point_g2;
+        let z = self.signature;
+
+        let h = challenge(p, &p1, &p2, &pk, &g2);
+
+        p * &z == &p2 + &(&p1 * &h) && &g * &z == &g2 + &(&pk * &h)
+    }
+}
+
+/// Splits `sk` into `num_shares` shares, any `threshold` of which
+/// are enough to open a capsule encrypted with the corresponding public key.
+///
+/// The shares are meant to be distributed between the members of a committee,
+/// after which `sk` can be destroyed.
+///
+/// Returns `None` if `threshold` is zero or greater than `num_shares`.
+pub fn split_secret_key(
+    sk: &SecretKey,
+    threshold: usize,
+    num_shares: usize,
+) -> Option<Box<[SecretKeyShare]>> {
+    if threshold == 0 || threshold > num_shares {
+        return None;
+    }
+
+    let mut coefficients = Vec::<CurveScalar>::with_capacity(threshold);
+    coefficients.push(sk.to_secret_scalar());
+    for _ in 1..threshold {
+        coefficients.push(CurveScalar::random_nonzero());
+    }
+
+    let mut result = Vec::<SecretKeyShare>::with_capacity(num_shares);
+    for i in 1..=num_shares as u32 {
+        let share = poly_eval(&coefficients, &CurveScalar::from_u32(i));
+        // The share is zero with a negligible probability.
+        result.push(SecretKeyShare::new(
+            i,
+            threshold as u32,
+            SecretKey::from_scalar(&share),
+        ));
+    }
+    Some(result.into_boxed_slice())
+}
+
+/// Combines the decryption shares into the base point multiplied by the shared key.
+pub(crate) fn combine_decryption_shares(
+    shares: &[DecryptionShare],
+) -> Result<CurvePoint, ThresholdDecryptionError> {
+    if shares.is_empty() {
+        return Err(ThresholdDecryptionError::NoDecryptionShares);
+    }
+
+    let threshold = shares[0].threshold;
+    if !shares.iter().all(|share| share.threshold == threshold) {
+        return Err(ThresholdDecryptionError::MismatchedDecryptionShares);
+    }
+
+    for (i, share) in shares.iter().enumerate() {
+        if share.index == 0 || shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(ThresholdDecryptionError::RepeatingDecryptionShares);
+        }
+    }
+
+    if shares.len() < threshold as usize {
+        return Err(ThresholdDecryptionError::NotEnoughDecryptionShares {
+            threshold: threshold as usize,
+            received: shares.len(),
+        });
+    }
+
+    let xs: Vec<CurveScalar> = shares
+        .iter()
+        .map(|share| CurveScalar::from_u32(share.index))
+        .collect();
+
+    let mut combined = CurvePoint::identity();
+    for (i, share) in shares.iter().enumerate() {
+        // The indices are distinct and non-zero, so the coefficient always exists.
+        let lambda_i = lambda_coeff(&xs, i).unwrap();
+        combined = &combined + &(&share.point_p1 * &lambda_i);
+    }
+
+    Ok(combined)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{split_secret_key, DecryptionShare, ThresholdDecryptionError};
+    use crate::{
+        decrypt_original_threshold, encrypt, Parameters, PublicKey, SecretKey, SerializableToArray,
+    };
+
+    #[test]
+    fn test_threshold_decryption() {
+        let params = Parameters::new();
+        let sk = SecretKey::random();
+        let pk = PublicKey::from_secret_key(&sk);
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&params, &pk, plaintext).unwrap();
+
+        let key_shares = split_secret_key(&sk, 3, 5).unwrap();
+        assert!(split_secret_key(&sk, 0, 5).is_none());
+        assert!(split_secret_key(&sk, 6, 5).is_none());
+        let public_shares: Vec<_> = key_shares.iter().map(|s| s.public_share()).collect();
+
+        let shares: Vec<DecryptionShare> = key_shares[1..4]
+            .iter()
+            .map(|key_share| key_share.decryption_share(&capsule))
+            .collect();
+
+        for share in shares.iter() {
+            let public_share = &public_shares[share.index() as usize - 1];
+            assert!(share.verify(&capsule, public_share));
+            assert!(!share.verify(&capsule, &public_shares[0]));
+
+            let share_back = DecryptionShare::from_array(&share.to_array()).unwrap();
+            assert_eq!(share, &share_back);
+        }
+
+        let plaintext_back = decrypt_original_threshold(&capsule, &shares, &ciphertext).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        assert_eq!(
+            decrypt_original_threshold(&capsule, &shares[..2], &ciphertext),
+            Err(ThresholdDecryptionError::NotEnoughDecryptionShares {
+                threshold: 3,
+                received: 2
+            })
+        );
+
+        let repeating = [shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert_eq!(
+            decrypt_original_threshold(&capsule, &repeating, &ciphertext),
+            Err(ThresholdDecryptionError::RepeatingDecryptionShares)
+        );
+
+        // A share made for another capsule fails the proof and spoils the decryption.
+        let (other_capsule, _) = encrypt(&params, &pk, plaintext).unwrap();
+        let wrong_share = key_shares[0].decryption_share(&other_capsule);
+        assert!(!wrong_share.verify(&capsule, &public_shares[0]));
+        let spoiled = [wrong_share, shares[1].clone(), shares[2].clone()];
+        assert_eq!(
+            decrypt_original_threshold(&capsule, &spoiled, &ciphertext),
+            Err(ThresholdDecryptionError::OnDecryption)
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use core::ops::Sub;
 use generic_array::sequence::Split;
 use generic_array::{ArrayLength, GenericArray};
-use typenum::{Diff, Unsigned, U1};
+use typenum::{Diff, Unsigned, U1, U4, U8};
 
 /// A trait denoting that the object can be serialized to/from an array of bytes
 /// with size known at compile time.
@@ -73,6 +73,34 @@ impl SerializableToArray for bool {
     }
 }
 
+impl SerializableToArray for u32 {
+    type Size = U4;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        GenericArray::<u8, Self::Size>::clone_from_slice(&self.to_be_bytes())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(arr.as_slice());
+        Some(u32::from_be_bytes(bytes))
+    }
+}
+
+impl SerializableToArray for u64 {
+    type Size = U8;
+
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        GenericArray::<u8, Self::Size>::clone_from_slice(&self.to_be_bytes())
+    }
+
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Option<Self> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(arr.as_slice());
+        Some(u64::from_be_bytes(bytes))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 